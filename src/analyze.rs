@@ -0,0 +1,247 @@
+use crate::encoding::Char;
+use crate::period;
+use std::collections::HashMap;
+
+/// Raw index of coincidence of English text.
+pub const ENGLISH_IOC: f64 = 0.0667;
+
+const MAX_PERIOD: usize = 15;
+
+/// English statistics the features are compared against.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    /// Letter probabilities, in descending order.
+    pub letters: Vec<f64>,
+    /// Probability of the letter written as each cipher symbol, when the
+    /// ciphertext is written in English letters.
+    pub aligned: Option<Vec<f64>>,
+    /// Log10 probability of every symbol bigram, `bigrams[a * symbols + b]`,
+    /// when the ciphertext is written in English letters.
+    pub bigrams: Option<Vec<f64>>,
+}
+
+/// ACA style statistics of a ciphertext.
+#[derive(Debug, Clone)]
+pub struct Features {
+    pub length: usize,
+    pub symbols: usize,
+    /// Raw index of coincidence.
+    pub ioc: f64,
+    /// Best average column IoC over periods 2 to 15, and its period.
+    pub max_periodic_ioc: f64,
+    pub max_period: usize,
+    /// Best rate of coincidences between the text and itself shifted, and its shift.
+    pub max_kappa: f64,
+    pub max_kappa_shift: usize,
+    /// IoC of all digraphs, and of the digraphs at even offsets.
+    pub dic: f64,
+    pub edi: f64,
+    /// Share of trigrams that occur more than once.
+    pub long_repeat: f64,
+    /// Share of repeated trigram pairs an odd distance apart.
+    pub odd_repeats: f64,
+    /// Mean log10 English bigram probability, when written in letters.
+    pub ldi: Option<f64>,
+    pub doubled: bool,
+    /// Doubled symbols within the pairs at even offsets, never seen in Playfair.
+    pub even_doubled: bool,
+    pub even_length: bool,
+    /// Chi-square of the symbol counts against English letters, as written.
+    pub chi_square: Option<f64>,
+    /// Chi-square of the sorted symbol counts against sorted English letters,
+    /// unchanged by monoalphabetic substitution.
+    pub sorted_chi_square: f64,
+    /// Shannon entropy in bits per symbol.
+    pub entropy: f64,
+}
+
+fn chi_square(observed: &[usize], expected: &[f64], total: usize) -> f64 {
+    observed
+        .iter()
+        .zip(expected.iter())
+        .filter(|(_, &p)| p > 0.0)
+        .map(|(&o, &p)| {
+            let e = p * total as f64;
+            (o as f64 - e).powi(2) / e
+        })
+        .sum()
+}
+
+impl Features {
+    pub fn new(cipher: &[Char], reference: &Reference) -> Self {
+        let len = cipher.len();
+        let mut counts = vec![0usize; cipher.iter().map(|&c| c as usize + 1).max().unwrap_or(0)];
+        for &c in cipher.iter() {
+            counts[c as usize] += 1;
+        }
+
+        let mut max_periodic_ioc = 0.0;
+        let mut max_period = 1;
+        for (period, average) in period::ioc_scan(cipher, MAX_PERIOD).into_iter().skip(1) {
+            if average > max_periodic_ioc {
+                max_periodic_ioc = average;
+                max_period = period;
+            }
+        }
+
+        let mut max_kappa = 0.0;
+        let mut max_kappa_shift = 0;
+        for shift in 1..=MAX_PERIOD.min(len.saturating_sub(1)) {
+            let matches = (0..len - shift).filter(|&i| cipher[i] == cipher[i + shift]).count();
+            let kappa = matches as f64 / (len - shift) as f64;
+            if kappa > max_kappa {
+                max_kappa = kappa;
+                max_kappa_shift = shift;
+            }
+        }
+
+        let mut trigrams: HashMap<&[Char], Vec<usize>> = HashMap::new();
+        for (index, trigram) in cipher.windows(3).enumerate() {
+            trigrams.entry(trigram).or_default().push(index);
+        }
+        let repeated = trigrams.values().filter(|p| p.len() > 1).map(|p| p.len()).sum::<usize>();
+        let (mut pairs, mut odd) = (0, 0);
+        for positions in trigrams.values() {
+            for (i, a) in positions.iter().enumerate() {
+                for b in positions[i + 1..].iter() {
+                    pairs += 1;
+                    odd += (b - a) % 2;
+                }
+            }
+        }
+
+        let ldi = reference.bigrams.as_ref().map(|bigrams| {
+            let symbols = (bigrams.len() as f64).sqrt() as usize;
+            let pairs = cipher.windows(2);
+            let count = pairs.len().max(1);
+            pairs.map(|p| bigrams[p[0] as usize * symbols + p[1] as usize]).sum::<f64>() / count as f64
+        });
+
+        let mut sorted = counts.clone();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+        let mut letters = reference.letters.clone();
+        letters.resize(sorted.len().max(letters.len()), 0.0);
+        sorted.resize(letters.len(), 0);
+
+        let entropy = counts
+            .iter()
+            .filter(|&&n| n > 0)
+            .map(|&n| {
+                let p = n as f64 / len as f64;
+                -p * p.log2()
+            })
+            .sum();
+
+        Self {
+            length: len,
+            symbols: counts.iter().filter(|&&n| n > 0).count(),
+            ioc: period::ioc_of_counts(counts.iter().copied()),
+            max_periodic_ioc,
+            max_period,
+            max_kappa,
+            max_kappa_shift,
+            dic: period::ioc(cipher.windows(2)),
+            edi: period::ioc(cipher.chunks_exact(2)),
+            long_repeat: repeated as f64 / len.saturating_sub(2).max(1) as f64,
+            odd_repeats: if pairs == 0 { 0.0 } else { odd as f64 / pairs as f64 },
+            ldi,
+            doubled: cipher.windows(2).any(|p| p[0] == p[1]),
+            even_doubled: cipher.chunks_exact(2).any(|p| p[0] == p[1]),
+            even_length: len.is_multiple_of(2),
+            chi_square: reference.aligned.as_ref().map(|aligned| {
+                let mut counts = counts.clone();
+                counts.resize(aligned.len().max(counts.len()), 0);
+                chi_square(&counts, aligned, len)
+            }),
+            sorted_chi_square: chi_square(&sorted, &letters, len),
+            entropy,
+        }
+    }
+}
+
+/// Cipher families told apart by `rank`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    Monoalphabetic,
+    Homophonic,
+    Periodic,
+    Transposition,
+    Fractionated,
+    Playfair,
+}
+
+impl std::fmt::Display for Family {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Family::Monoalphabetic => "monoalphabetic",
+            Family::Homophonic => "homophonic",
+            Family::Periodic => "periodic",
+            Family::Transposition => "transposition",
+            Family::Fractionated => "fractionated",
+            Family::Playfair => "playfair",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 1 at `target`, falling off like a gaussian of width `spread`.
+fn near(value: f64, target: f64, spread: f64) -> f64 {
+    (-((value - target) / spread).powi(2)).exp()
+}
+
+/// 1 well above `threshold`, 0 well below.
+fn above(value: f64, threshold: f64, spread: f64) -> f64 {
+    1.0 / (1.0 + (-(value - threshold) / spread).exp())
+}
+
+/// How well a chi-square against English letters fits, 1 for typical
+/// English samples.
+fn fits(chi_square: f64) -> f64 {
+    // 25 degrees of freedom, English samples of a few hundred letters
+    // stay below about twice that
+    1.0 - above(chi_square, 60.0, 10.0)
+}
+
+/// Ranks cipher families by how well the features match them, best first.
+/// Scores are between 0 and 1 and only comparable with each other.
+pub fn rank(features: &Features) -> Vec<(Family, f64)> {
+    let f = features;
+    let english_ioc = near(f.ioc, ENGLISH_IOC, 0.008);
+    let flat_ioc = 1.0 - english_ioc;
+    let letters = 1.0 - above(f.symbols as f64, 26.5, 0.5);
+    let english_letters = f.chi_square.map_or(0.0, fits);
+    let periodic_peak = f64::max(
+        near(f.max_periodic_ioc, ENGLISH_IOC, 0.01),
+        above(f.max_periodic_ioc, ENGLISH_IOC, 0.003),
+    ) * above(f.max_periodic_ioc - f.ioc, 0.01, 0.003);
+
+    let mut scores = vec![
+        (
+            Family::Monoalphabetic,
+            english_ioc * letters * fits(f.sorted_chi_square) * (1.0 - english_letters),
+        ),
+        (
+            Family::Homophonic,
+            above(f.symbols as f64, 30.0, 1.0) * (1.0 - above(f.ioc, 0.055, 0.004)),
+        ),
+        (Family::Periodic, letters * flat_ioc * periodic_peak),
+        (Family::Transposition, english_ioc * letters * english_letters),
+        (
+            Family::Fractionated,
+            f64::max(
+                1.0 - above(f.symbols as f64, 10.5, 0.5),
+                0.5 * letters * near(f.ioc, 0.045, 0.006) * (1.0 - periodic_peak),
+            ),
+        ),
+        (
+            Family::Playfair,
+            (1.0 - above(f.symbols as f64, 25.5, 0.5))
+                * above(f.symbols as f64, 20.0, 1.0)
+                * if f.even_length && !f.even_doubled { 1.0 } else { 0.0 }
+                * near(f.ioc, 0.051, 0.006)
+                * (1.0 - periodic_peak),
+        ),
+    ];
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores
+}
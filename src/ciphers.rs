@@ -0,0 +1,515 @@
+use crate::encoding::{Alphabet, Char, Encoding};
+use crate::error::{Error, Result};
+use crate::key::{Crib, Key, Keys};
+use crate::periodic::Variant;
+use crate::transposition::Kind;
+use crate::util::Random;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// A cipher family: how its keys encrypt and decrypt, and how the
+/// climber moves between them.
+pub trait Cipher: Clone {
+    type Key: Clone + std::fmt::Debug + Send + Sync;
+    /// A step from a key to one of its neighbours.
+    type Move: Copy + std::fmt::Debug;
+
+    /// Fails for keys that cannot encrypt the plaintext.
+    fn encrypt(&self, key: &Self::Key, plaintext: &[Char], ciphertext: &mut [Char], rng: &mut Random) -> Result<()>;
+
+    fn decrypt(&self, key: &Self::Key, ciphertext: &[Char], plaintext: &mut [Char]);
+
+    fn random_key(&self, rng: &mut Random) -> Self::Key;
+
+    /// Cipher symbols keys are defined for.
+    fn symbols(&self) -> usize;
+
+    /// Positions of every symbol in the ciphertext, for the symbols `changed`
+    /// reports. By default the symbols are the cipher characters.
+    fn positions(&self, ciphertext: &[Char]) -> Vec<Vec<usize>> {
+        let mut positions = vec![Vec::new(); self.symbols()];
+        for (index, &c) in ciphertext.iter().enumerate() {
+            if let Some(positions) = positions.get_mut(c as usize) {
+                positions.push(index);
+            }
+        }
+        positions
+    }
+
+    /// Moves to every neighbour of the key, in the order they are tried.
+    fn moves(&self, key: &Self::Key) -> Vec<Self::Move>;
+
+    /// Applies a move, returning the move that undoes it.
+    fn apply(&self, key: &mut Self::Key, step: Self::Move) -> Self::Move;
+
+    /// A random change larger than a move, to leave a local optimum.
+    fn mutate(&self, key: &mut Self::Key, rng: &mut Random);
+
+    /// Restores parts of the key fixed by constraints such as cribs.
+    fn constrain(&self, _key: &mut Self::Key) {}
+
+    /// Pushes the symbols, numbered as in `positions`, whose plaintext
+    /// `step` changed in the applied key, with their new plaintext. The
+    /// climber then only rescores their positions. Returns false when the
+    /// whole text must be decrypted.
+    fn changed(&self, _key: &Self::Key, _step: Self::Move, _changed: &mut Vec<(usize, Char)>) -> bool {
+        false
+    }
+
+    /// Pushes the `(cipher symbol, plaintext)` assignments of the key that
+    /// `step` changes, as they are when called. Symbols of keys with several
+    /// parts are numbered across the parts. Tabu searches forbid moves back
+    /// to recently replaced assignments, keys without any are never tabu.
+    fn assignments(&self, _key: &Self::Key, _step: Self::Move, _assignments: &mut Vec<(usize, Char)>) {}
+
+    fn format_key(&self, key: &Self::Key) -> String;
+
+    fn parse_key(&self, text: &str) -> Result<Self::Key>;
+}
+
+/// Homophonic substitution, every cipher symbol decrypts to one output
+/// character. Cribbed symbols keep their plaintext.
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    pub cipher_alphabet: Alphabet,
+    pub output_alphabet: Alphabet,
+    pub output_encoding: Encoding,
+    pub crib: Crib,
+    pub fix_key: Key,
+}
+
+impl Substitution {
+    pub fn new(cipher_alphabet: Alphabet, output_encoding: Encoding) -> Self {
+        let len = cipher_alphabet.len();
+        Self {
+            cipher_alphabet,
+            output_alphabet: output_encoding.base_alphabet(),
+            output_encoding,
+            crib: Crib::new(len),
+            fix_key: Key::new(len),
+        }
+    }
+
+    pub fn crib_char(&mut self, encoded_char: Char, decoded_char: Char) {
+        self.fix_key[encoded_char as usize] = decoded_char;
+        self.crib.fix(encoded_char as usize);
+    }
+}
+
+impl Cipher for Substitution {
+    type Key = Key;
+    /// Swaps the plaintext of two cipher symbols.
+    type Move = (usize, usize);
+
+    fn encrypt(&self, key: &Key, plaintext: &[Char], ciphertext: &mut [Char], rng: &mut Random) -> Result<()> {
+        key.encode(plaintext, ciphertext, &self.output_alphabet, rng)
+    }
+
+    fn decrypt(&self, key: &Key, ciphertext: &[Char], plaintext: &mut [Char]) {
+        key.decode(ciphertext, plaintext);
+    }
+
+    fn random_key(&self, rng: &mut Random) -> Key {
+        let mut key = Key::random(&self.cipher_alphabet, &self.output_alphabet, rng);
+        self.constrain(&mut key);
+        key
+    }
+
+    fn symbols(&self) -> usize {
+        self.cipher_alphabet.len()
+    }
+
+    fn moves(&self, _key: &Key) -> Vec<(usize, usize)> {
+        let loose = &self.crib.loose;
+        let mut moves = Vec::with_capacity(loose.len() * loose.len() / 2);
+        for (index, &i) in loose.iter().enumerate() {
+            moves.extend(loose[index + 1..].iter().map(|&j| (i, j)));
+        }
+        moves
+    }
+
+    #[inline(always)]
+    fn apply(&self, key: &mut Key, (i, j): (usize, usize)) -> (usize, usize) {
+        key.swap(i, j);
+        (i, j)
+    }
+
+    fn mutate(&self, key: &mut Key, rng: &mut Random) {
+        key.random_putc(&self.output_alphabet, rng);
+        self.constrain(key);
+    }
+
+    fn constrain(&self, key: &mut Key) {
+        for &index in self.crib.fixed.iter() {
+            key[index] = self.fix_key[index];
+        }
+    }
+
+    #[inline(always)]
+    fn changed(&self, key: &Key, (i, j): (usize, usize), changed: &mut Vec<(usize, Char)>) -> bool {
+        changed.push((i, key[i]));
+        changed.push((j, key[j]));
+        true
+    }
+
+    #[inline(always)]
+    fn assignments(&self, key: &Key, (i, j): (usize, usize), assignments: &mut Vec<(usize, Char)>) {
+        assignments.push((i, key[i]));
+        assignments.push((j, key[j]));
+    }
+
+    /// The plaintext of every cipher symbol, in alphabet order.
+    fn format_key(&self, key: &Key) -> String {
+        let mut plaintext = vec![0; key.len()];
+        key.decode(&self.cipher_alphabet.char_vec(), &mut plaintext);
+        self.output_encoding.decode_str(&plaintext)
+    }
+
+    fn parse_key(&self, text: &str) -> Result<Key> {
+        let plaintext = self.output_encoding.encode_str(text)?;
+        if plaintext.len() != self.symbols() {
+            return Err(Error::Invalid(format!(
+                "Expected {} key characters, found {}",
+                self.symbols(),
+                plaintext.len()
+            )));
+        }
+        let mut key = Key::new(plaintext.len());
+        for (index, &c) in plaintext.iter().enumerate() {
+            key[index] = c;
+        }
+        Ok(key)
+    }
+}
+
+/// Periodic polyalphabetic cipher, position `i` decrypted by column key
+/// `i % period`. Shift variants move by changing the shift of a column,
+/// general keys by swapping within a column. Symbols are numbered
+/// `column * len + cipher character` across the columns.
+#[derive(Debug, Clone)]
+pub struct Polyalphabetic {
+    pub variant: Variant,
+    pub period: usize,
+    pub cipher_alphabet: Alphabet,
+    pub output_encoding: Encoding,
+    pub output_alphabet: Alphabet,
+}
+
+impl Polyalphabetic {
+    pub fn new(variant: Variant, period: usize, cipher_alphabet: Alphabet, output_encoding: Encoding) -> Result<Self> {
+        let output_alphabet = output_encoding.base_alphabet();
+        if period == 0 {
+            return Err(Error::Invalid("Period may not be zero".into()));
+        }
+        if variant.is_shift() && cipher_alphabet.len() != output_alphabet.len() {
+            return Err(Error::Invalid("Shift variants need alphabets of equal length".into()));
+        }
+        Ok(Self {
+            variant,
+            period,
+            cipher_alphabet,
+            output_encoding,
+            output_alphabet,
+        })
+    }
+
+    /// The shift of every column of a shift variant key.
+    pub fn shifts(&self, keys: &Keys) -> Vec<usize> {
+        (0..keys.count()).map(|column| self.variant.shift_of(&keys[column])).collect()
+    }
+}
+
+/// `Swap(column, i, j)` swaps within a column key, `Shift(column, shift)`
+/// replaces a column key by a shifted alphabet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Swap(usize, usize, usize),
+    Shift(usize, usize),
+}
+
+impl Cipher for Polyalphabetic {
+    type Key = Keys;
+    type Move = Step;
+
+    fn encrypt(&self, keys: &Keys, plaintext: &[Char], ciphertext: &mut [Char], rng: &mut Random) -> Result<()> {
+        let inverses = (0..keys.count()).map(|k| keys[k].inverse(&self.output_alphabet)).collect::<Vec<_>>();
+        for (index, &c) in plaintext.iter().enumerate() {
+            let column = index % inverses.len();
+            ciphertext[index] = *inverses[column][c as usize].choose(rng).ok_or_else(|| {
+                Error::Invalid(format!("No cipher symbol of column {} decrypts to {}", column, self.output_encoding.decode_str(&[c])))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn decrypt(&self, keys: &Keys, ciphertext: &[Char], plaintext: &mut [Char]) {
+        keys.decode(ciphertext, plaintext);
+    }
+
+    fn random_key(&self, rng: &mut Random) -> Keys {
+        if !self.variant.is_shift() {
+            return Keys::random(self.period, &self.cipher_alphabet, &self.output_alphabet, rng);
+        }
+        let mut keys = Keys::new(self.period, self.symbols());
+        for column in 0..self.period {
+            keys[column] = self.variant.shift_key(rng.gen_range(0..self.symbols()), self.symbols());
+        }
+        keys
+    }
+
+    fn symbols(&self) -> usize {
+        self.cipher_alphabet.len()
+    }
+
+    /// Positions of every column's cipher characters.
+    fn positions(&self, ciphertext: &[Char]) -> Vec<Vec<usize>> {
+        let len = self.symbols();
+        let mut positions = vec![Vec::new(); self.period * len];
+        for (index, &c) in ciphertext.iter().enumerate() {
+            positions[index % self.period * len + c as usize].push(index);
+        }
+        positions
+    }
+
+    fn moves(&self, _keys: &Keys) -> Vec<Step> {
+        let len = self.symbols();
+        let mut moves = Vec::new();
+        for column in 0..self.period {
+            if self.variant.is_shift() {
+                moves.extend((0..len).map(|shift| Step::Shift(column, shift)));
+            } else {
+                for i in 0..len {
+                    moves.extend((i + 1..len).map(|j| Step::Swap(column, i, j)));
+                }
+            }
+        }
+        moves
+    }
+
+    fn apply(&self, keys: &mut Keys, step: Step) -> Step {
+        match step {
+            Step::Swap(column, i, j) => {
+                keys[column].swap(i, j);
+                step
+            }
+            Step::Shift(column, shift) => {
+                let previous = self.variant.shift_of(&keys[column]);
+                keys[column] = self.variant.shift_key(shift, self.symbols());
+                Step::Shift(column, previous)
+            }
+        }
+    }
+
+    fn mutate(&self, keys: &mut Keys, rng: &mut Random) {
+        let column = rng.gen_range(0..self.period);
+        if self.variant.is_shift() {
+            keys[column] = self.variant.shift_key(rng.gen_range(0..self.symbols()), self.symbols());
+        } else {
+            keys.random_putc(column, &self.output_alphabet, rng);
+        }
+    }
+
+    fn changed(&self, keys: &Keys, step: Step, changed: &mut Vec<(usize, Char)>) -> bool {
+        self.assignments(keys, step, changed);
+        true
+    }
+
+    fn assignments(&self, keys: &Keys, step: Step, assignments: &mut Vec<(usize, Char)>) {
+        let len = self.symbols();
+        match step {
+            Step::Swap(column, i, j) => {
+                assignments.push((column * len + i, keys[column][i]));
+                assignments.push((column * len + j, keys[column][j]));
+            }
+            Step::Shift(column, _) => {
+                assignments.extend((0..len).map(|symbol| (column * len + symbol, keys[column][symbol])));
+            }
+        }
+    }
+
+    /// The key word of shift variants, otherwise the plaintext of every
+    /// cipher symbol per column, separated by commas.
+    fn format_key(&self, keys: &Keys) -> String {
+        if self.variant.is_shift() {
+            let word = self.shifts(keys).iter().map(|&s| s as Char).collect::<Vec<_>>();
+            return self.output_encoding.decode_str(&word);
+        }
+        let symbols = self.cipher_alphabet.char_vec();
+        let mut plaintext = vec![0; symbols.len()];
+        (0..keys.count())
+            .map(|column| {
+                keys[column].decode(&symbols, &mut plaintext);
+                self.output_encoding.decode_str(&plaintext)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn parse_key(&self, text: &str) -> Result<Keys> {
+        let mut keys = Keys::new(self.period, self.symbols());
+        if self.variant.is_shift() {
+            let word = self.output_encoding.encode_str(text)?;
+            if word.len() != self.period {
+                return Err(Error::Invalid(format!("Expected a key word of {} characters", self.period)));
+            }
+            for (column, &shift) in word.iter().enumerate() {
+                keys[column] = self.variant.shift_key(shift as usize, self.symbols());
+            }
+            return Ok(keys);
+        }
+        let columns = text.split(',').collect::<Vec<_>>();
+        if columns.len() != self.period {
+            return Err(Error::Invalid(format!("Expected {} column keys", self.period)));
+        }
+        for (column, part) in columns.iter().enumerate() {
+            let plaintext = self.output_encoding.encode_str(part)?;
+            if plaintext.len() != self.symbols() {
+                return Err(Error::Invalid(format!("Expected {} characters per column key", self.symbols())));
+            }
+            for (index, &c) in plaintext.iter().enumerate() {
+                keys[column][index] = c;
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// Columnar transpositions of the given kind, with one rank vector per
+/// key. Moves swap two ranks, move a column elsewhere, or set a single
+/// rank of a Myszkowski key.
+#[derive(Debug, Clone)]
+pub struct Columnar {
+    pub kind: Kind,
+    pub widths: Vec<usize>,
+}
+
+/// A move within one rank vector of a `Columnar` key, whose index is the
+/// first field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnarMove {
+    /// `Swap(key, i, j)` swaps the ranks of columns `i` and `j`.
+    Swap(usize, usize, usize),
+    /// `Insert(key, i, j)` moves column `i` to `j`, shifting the columns
+    /// between.
+    Insert(usize, usize, usize),
+    /// `Rank(key, i, rank)` sets the rank of column `i`, only for
+    /// Myszkowski keys.
+    Rank(usize, usize, usize),
+}
+
+impl Columnar {
+    pub fn new(kind: Kind, widths: Vec<usize>) -> Result<Self> {
+        if widths.len() != kind.keys() || widths.contains(&0) {
+            return Err(Error::Invalid(format!("Expected {} key width(s) above zero", kind.keys())));
+        }
+        Ok(Self { kind, widths })
+    }
+}
+
+impl Cipher for Columnar {
+    type Key = Vec<Vec<usize>>;
+    type Move = ColumnarMove;
+
+    fn encrypt(&self, key: &Self::Key, plaintext: &[Char], ciphertext: &mut [Char], _rng: &mut Random) -> Result<()> {
+        let transposition = self.kind.build(plaintext.len(), key).map_err(|e| Error::Invalid(e.into()))?;
+        transposition.inverse().apply_to(plaintext, ciphertext);
+        Ok(())
+    }
+
+    fn decrypt(&self, key: &Self::Key, ciphertext: &[Char], plaintext: &mut [Char]) {
+        self.kind.decrypt(key, ciphertext, plaintext);
+    }
+
+    fn random_key(&self, rng: &mut Random) -> Self::Key {
+        self.widths
+            .iter()
+            .map(|&width| {
+                let mut ranks = (0..width).collect::<Vec<_>>();
+                ranks.shuffle(rng);
+                ranks
+            })
+            .collect()
+    }
+
+    /// Transpositions do not substitute symbols.
+    fn symbols(&self) -> usize {
+        0
+    }
+
+    /// Every move changing the key.
+    fn moves(&self, key: &Self::Key) -> Vec<ColumnarMove> {
+        let mut moves = Vec::new();
+        for (k, ranks) in key.iter().enumerate() {
+            let width = ranks.len();
+            for i in 0..width {
+                for j in 0..width {
+                    if i < j && ranks[i] != ranks[j] {
+                        moves.push(ColumnarMove::Swap(k, i, j));
+                    }
+                    if i != j {
+                        moves.push(ColumnarMove::Insert(k, i, j));
+                    }
+                    if self.kind == Kind::Myszkowski && ranks[i] != j {
+                        moves.push(ColumnarMove::Rank(k, i, j));
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    fn apply(&self, key: &mut Self::Key, step: ColumnarMove) -> ColumnarMove {
+        match step {
+            ColumnarMove::Swap(k, i, j) => {
+                key[k].swap(i, j);
+                step
+            }
+            ColumnarMove::Insert(k, i, j) => {
+                let rank = key[k].remove(i);
+                key[k].insert(j, rank);
+                ColumnarMove::Insert(k, j, i)
+            }
+            ColumnarMove::Rank(k, i, rank) => ColumnarMove::Rank(k, i, std::mem::replace(&mut key[k][i], rank)),
+        }
+    }
+
+    fn mutate(&self, key: &mut Self::Key, rng: &mut Random) {
+        let k = rng.gen_range(0..key.len());
+        let width = key[k].len();
+        key[k].swap(rng.gen_range(0..width), rng.gen_range(0..width));
+    }
+
+    /// Ranks separated by spaces, keys by commas.
+    fn format_key(&self, key: &Self::Key) -> String {
+        key.iter()
+            .map(|ranks| ranks.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn parse_key(&self, text: &str) -> Result<Self::Key> {
+        let key = text
+            .split(',')
+            .map(|ranks| {
+                ranks
+                    .split_whitespace()
+                    .map(|r| r.parse::<usize>().map_err(|_| Error::Invalid(format!("Invalid rank: {}", r))))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let widths = key.iter().map(|ranks| ranks.len()).collect::<Vec<_>>();
+        if widths != self.widths {
+            return Err(Error::Invalid(format!("Expected keys of widths {:?}", self.widths)));
+        }
+        for ranks in key.iter() {
+            let mut sorted = ranks.clone();
+            sorted.sort_unstable();
+            sorted.dedup();
+            let ties = sorted.len() < ranks.len();
+            if ranks.iter().any(|&r| r >= ranks.len()) || (ties && self.kind != Kind::Myszkowski) {
+                return Err(Error::Invalid(format!("Invalid ranks: {:?}", ranks)));
+            }
+        }
+        Ok(key)
+    }
+}
@@ -0,0 +1,707 @@
+use cipher::encoding::Combine;
+use cipher::genetic::Crossover;
+use cipher::mode::Mode;
+use cipher::periodic::Variant;
+use cipher::schedule::{acceptance_rises, Cooling, Schedule};
+use cipher::search::{Search, Strategy};
+use cipher::stats::Smoothing;
+use cipher::tempering::Tempering;
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: cipher <COMMAND> [OPTIONS]
+
+Commands:
+  solve <CIPHERTEXT>       Solve a substitution, periodic or transposition cipher
+  analyze <CIPHERTEXT>     Print ciphertext statistics and likely cipher families
+  encrypt <PLAINTEXT>      Encrypt a text with a substitution, periodic or transposition cipher
+  build-ngrams <CORPUS>    Count n-grams in a corpus and write a gram file
+  convert-ngrams <GRAMS>   Convert a gram file into a binary model
+  help                     Print this message
+
+Alphabets may be given literally or by name (latin, zodiac, symbol).
+
+Solve options:
+  --mode <MODE>                  substitution, a periodic mode: periodic (any key
+                                 per column), vigenere, beaufort, variant, or a
+                                 transposition: columnar, double-columnar, myszkowski,
+                                 route (rank every route transposition)
+                                 [default: substitution]
+  --period <N>                   Key period of periodic modes [default: estimated]
+  --width <N|A-B>                Key widths of transpositions, grid widths of routes
+                                 [default: 2-12]
+  --cipher-alphabet <ALPHABET>   Ciphertext alphabet
+                                 [default: zodiac, latin for other modes]
+  --output-alphabet <ALPHABET>   Plaintext alphabet [default: latin]
+  --order <N>                    N-gram order [default: 4]
+  --ngrams <PATH>                N-gram file or binary model [default: ./data/en_<N>gram.txt]
+  --smoothing <MODEL>            N-gram scores: inverse, add-k[:K], good-turing,
+                                 kneser-ney[:D] [default: inverse]
+  --interpolate <N:W[:PATH]>     Add an order N model with weight W, may be repeated,
+                                 replaces --order and --ngrams
+  --combine <MODE>               Combine interpolated models: linear, probability,
+                                 backoff[:PENALTY] [default: linear]
+  --letters <PATH>               Letter frequency file [default: ./data/en_1gram.txt]
+  --homophones <RATIO>           Homophone ratio of the key [default: 0.2]
+  --cycles <N>                   Climbing cycles [default: 1000]
+  --mutate-cycle <N>             Rejections before mutating the key [default: 1000]
+  --derive-cycle <N>             Rejections before deriving the key [default: 100000]
+  --crib <OFFSET:TEXT>           Known plaintext at an offset, may be repeated
+  --transposition <SPEC>         Undo a transposition before substitution, may be
+                                 repeated: identity, period:P, or a route
+                                 NAME:W[:DX:DY][+ORIENTATION] with names rows,
+                                 columns, boustrophedon, spiral, knight, diagonal
+                                 and orientations mirror, flip, rotate90, rotate180,
+                                 rotate270, transpose, antitranspose; segments
+                                 are joined as SPEC@LEN/.../SPEC, a last SPEC@LEN
+                                 leaves the rest in place
+  --anneal-transpositions <BOOL> Switch between transpositions while climbing
+                                 instead of solving each one [default: false]
+  --search <STRATEGY>            Moves taken while climbing: steepest, first
+                                 (first improvement), lavy[:P] (Lavy's acceptance
+                                 with threshold P), anneal (Metropolis), threshold
+                                 (losses below the temperature), record[:D] (within
+                                 D of the best), late[:L] (late acceptance over L
+                                 moves), tabu[:T] (best move not undoing one of
+                                 the last T moves, not for transposition modes)
+                                 [default: lavy:0.0085]
+  --threshold <P>                Same as --search lavy:P
+  --temperature <T>              Start temperature [default: 3500 for lavy, 5 for
+                                 anneal, 10 for threshold]
+  --end-temperature <T>          Temperature at the last cycle [default: T / 100,
+                                 T * 100 for lavy, whose acceptance falls as the
+                                 temperature rises]
+  --schedule <SCHEDULE>          Temperature over the cycles: constant, linear,
+                                 geometric, logarithmic, adaptive[:RATE] (follow an
+                                 acceptance rate falling from RATE to 0),
+                                 reheat[:PATIENCE] (geometric, back to the start
+                                 after PATIENCE cycles without a new best)
+                                 [default: constant]
+  --calibrate <START:END>        Pick the start and end temperatures at which these
+                                 shares of worsening moves from random keys are
+                                 accepted, e.g. 0.8:0.01, instead of --temperature
+                                 and --end-temperature
+  --tempering <N>                Parallel tempering with N chains at temperatures
+                                 from the end to the start temperature, exchanged
+                                 between neighbours, replaces --restarts and
+                                 --threads, needs --search anneal or threshold
+                                 [default: 0, off]
+  --exchange-cycle <N>           Cycles between exchanges of tempered chains
+                                 [default: 10]
+  --genetic <N>                  Evolve a population of N substitution keys for
+                                 --cycles generations instead of climbing,
+                                 replaces --restarts and --threads [default: 0, off]
+  --crossover <CROSSOVER>        Crossover of the genetic search: uniform, order
+                                 (keep a slice), position (keep random entries)
+                                 [default: order]
+  --tournament <N>               Keys drawn to pick each parent [default: 3]
+  --elites <N>                   Best keys kept every generation [default: 2]
+  --mutation <P>                 Probability of mutating a child [default: 0.5]
+  --seed <N>                     Random seed, printed when omitted
+  --restarts <N>                 Independent restarts [default: 1]
+  --share <P>                    Probability that a restart climbs from the best
+                                 key of all workers so far, mutated, instead of a
+                                 random key [default: 0]
+  --threads <N>                  Worker threads [default: available cores]
+  --leaderboard <N>              Distinct solutions to print [default: 5]
+
+Analyze options:
+  --cipher-alphabet <ALPHABET>   Ciphertext alphabet [default: zodiac]
+  --letters <PATH>               English letter counts [default: ./data/en_1gram.txt]
+  --bigrams <PATH>               English bigram counts [default: ./data/en_2gram.txt]
+  --max-period <N>               Largest period of the bigram repeat table [default: 40]
+  --shuffles <N>                 Shuffled ciphertexts of the repeat baseline [default: 100]
+  --width <N>                    Also count repeats along the diagonals of a grid
+                                 of N columns
+  --seed <N>                     Random seed of the shuffles, printed when omitted
+
+Encrypt options:
+  --mode <MODE>                  substitution, vigenere, beaufort, variant, columnar,
+                                 double-columnar, myszkowski, route (only apply
+                                 --transposition) [default: substitution]
+  --key <WORD[,WORD]>            Key word of shift and transposition modes, two for
+                                 double-columnar [default: random]
+  --period <N>                   Length of random key words [default: 6]
+  --transposition <SPEC>         Transpose the plaintext before substitution
+  --cipher-alphabet <ALPHABET>   Ciphertext alphabet
+                                 [default: zodiac, latin for other modes]
+  --output-alphabet <ALPHABET>   Plaintext alphabet [default: latin]
+  --out <PATH>                   Write the ciphertext to a file instead of stdout
+  --seed <N>                     Random seed, printed when omitted
+
+Build-ngrams options:
+  --order <N|A-B>                N-gram order or range of orders [default: 4]
+  --alphabet <ALPHABET>          Characters to count, lower case is folded [default: latin]
+  --min-count <N>                Drop grams counted fewer times [default: 1]
+  --top <N>                      Keep only the most frequent grams
+  --out <PATH>                   Output file, {n} is replaced by the order
+                                 [default: ./data/en_{n}gram.txt]
+  --overwrite <BOOL>             Replace existing files [default: false]
+
+Convert-ngrams options:
+  --order <N>                    N-gram order [default: 4]
+  --alphabet <ALPHABET>          Gram alphabet [default: latin]
+  --smoothing <MODEL>            N-gram scores, as for solve [default: inverse]
+  --out <PATH>                   Output file [default: <GRAMS>.bin]
+";
+
+/// Raw command line split into positionals and `--name value` options.
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    pub positional: Vec<String>,
+    pub options: Vec<(String, String)>,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                if let Some((name, value)) = name.split_once('=') {
+                    parsed.options.push((name.into(), value.into()));
+                    continue;
+                }
+                match args.next() {
+                    Some(value) => parsed.options.push((name.into(), value)),
+                    None => return Err(format!("Missing value for option --{}", name)),
+                }
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn check(&self, known: &[&str]) -> Result<(), String> {
+        for (name, _) in self.options.iter() {
+            if !known.contains(&name.as_str()) {
+                return Err(format!("Unknown option --{}", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn positional(&self, index: usize, name: &str) -> Result<String, String> {
+        self.positional
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("Missing argument <{}>", name))
+    }
+
+    pub fn optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Invalid value for option --{}: {}", name, value)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn string(&self, name: &str, default: &str) -> String {
+        self.value(name).unwrap_or(default).into()
+    }
+
+    pub fn get<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.value(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("Invalid value for option --{}: {}", name, value)),
+            None => Ok(default),
+        }
+    }
+}
+
+/// Resolves an alphabet name to its characters, or returns the argument verbatim.
+pub fn alphabet(name: &str) -> String {
+    match name {
+        "latin" => cipher::LATIN.into(),
+        "zodiac" => cipher::ZODIAC.into(),
+        "symbol" => cipher::SYMBOL.into(),
+        _ => name.into(),
+    }
+}
+
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+pub fn ngram_path(order: usize) -> String {
+    format!("./data/en_{}gram.txt", order)
+}
+
+/// Only substitution keys change the alphabet by default.
+fn default_cipher_alphabet(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Substitution => "zodiac",
+        _ => "latin",
+    }
+}
+
+/// Parses `N` or an inclusive range `A-B` of positive numbers.
+pub fn range(args: &Args, name: &str, default: &str) -> Result<Vec<usize>, String> {
+    let value = args.string(name, default);
+    let invalid = || format!("Invalid value for option --{}: {}", name, value);
+    let range = match value.split_once('-') {
+        Some((a, b)) => {
+            let a = a.parse::<usize>().map_err(|_| invalid())?;
+            let b = b.parse::<usize>().map_err(|_| invalid())?;
+            (a..=b).collect::<Vec<_>>()
+        }
+        None => vec![value.parse::<usize>().map_err(|_| invalid())?],
+    };
+    if range.is_empty() || range.contains(&0) {
+        return Err(invalid());
+    }
+    Ok(range)
+}
+
+/// Parses an optional temperature, which must not be negative.
+fn temperature(args: &Args, name: &str) -> Result<Option<f64>, String> {
+    let temperature = args.optional::<f64>(name)?;
+    if temperature.is_some_and(|t| t.is_nan() || t < 0.0) {
+        return Err(format!("Invalid value for option --{}: must not be negative", name));
+    }
+    Ok(temperature)
+}
+
+#[derive(Debug, Clone)]
+pub struct Solve {
+    pub mode: Mode,
+    pub period: Option<usize>,
+    pub widths: Vec<usize>,
+    pub ciphertext: String,
+    pub cipher_alphabet: String,
+    pub output_alphabet: String,
+    pub order: usize,
+    pub ngrams: String,
+    pub smoothing: Smoothing,
+    pub interpolate: Vec<(usize, f64, String)>,
+    pub combine: Combine,
+    pub letters: String,
+    pub homophone_ratio: f64,
+    pub cycle: usize,
+    pub mutate_cycle: usize,
+    pub derive_cycle: usize,
+    pub cribs: Vec<(usize, String)>,
+    pub transpositions: Vec<String>,
+    pub anneal_transpositions: bool,
+    pub search: Strategy,
+    pub temperature: Option<f64>,
+    pub end_temperature: Option<f64>,
+    pub cooling: Cooling,
+    /// Acceptance rates of worsening moves at the start and end temperatures.
+    pub calibrate: Option<(f64, f64)>,
+    /// Chains of parallel tempering, 0 when off.
+    pub tempering: usize,
+    pub exchange_cycle: usize,
+    /// Population of the genetic search, 0 when off.
+    pub genetic: usize,
+    pub crossover: Crossover,
+    pub tournament: usize,
+    pub elites: usize,
+    pub mutation: f64,
+    pub seed: Option<u64>,
+    pub restarts: usize,
+    /// Probability that a restart starts from the shared best key.
+    pub share: f64,
+    pub threads: usize,
+    pub leaderboard: usize,
+}
+
+impl Solve {
+    /// Temperatures of the search, defaulting to those suited to it.
+    pub fn schedule(&self) -> Schedule {
+        let start = self.temperature.unwrap_or_else(|| self.search.temperature());
+        let end = self.end_temperature.unwrap_or_else(|| Schedule::default_end(&self.search, start));
+        Schedule::new(self.cooling, start, end)
+    }
+
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        args.check(&[
+            "mode",
+            "period",
+            "width",
+            "cipher-alphabet",
+            "output-alphabet",
+            "order",
+            "ngrams",
+            "smoothing",
+            "interpolate",
+            "combine",
+            "letters",
+            "homophones",
+            "cycles",
+            "mutate-cycle",
+            "derive-cycle",
+            "crib",
+            "transposition",
+            "anneal-transpositions",
+            "search",
+            "threshold",
+            "temperature",
+            "end-temperature",
+            "schedule",
+            "calibrate",
+            "tempering",
+            "exchange-cycle",
+            "genetic",
+            "crossover",
+            "tournament",
+            "elites",
+            "mutation",
+            "seed",
+            "restarts",
+            "share",
+            "threads",
+            "leaderboard",
+        ])?;
+        let order = args.get("order", 4)?;
+        if order == 0 {
+            return Err("Invalid value for option --order: 0".into());
+        }
+        let homophone_ratio = args.get("homophones", 0.2)?;
+        if homophone_ratio < 0.0 {
+            return Err("Invalid value for option --homophones: must not be negative".into());
+        }
+        let mut interpolate = Vec::new();
+        for model in args.values("interpolate") {
+            let mut parts = model.splitn(3, ':');
+            let invalid = || format!("Invalid model, expected ORDER:WEIGHT[:PATH]: {}", model);
+            let order = parts.next().and_then(|n| n.parse().ok()).filter(|&n| n > 0);
+            let weight = parts.next().and_then(|w| w.parse().ok());
+            match (order, weight) {
+                (Some(order), Some(weight)) => {
+                    let path = parts.next().map(|p| p.into()).unwrap_or_else(|| ngram_path(order));
+                    interpolate.push((order, weight, path));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        let mut cribs = Vec::new();
+        for crib in args.values("crib") {
+            let (offset, text) = crib
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid crib, expected OFFSET:TEXT: {}", crib))?;
+            let offset = offset
+                .parse()
+                .map_err(|_| format!("Invalid crib offset: {}", offset))?;
+            cribs.push((offset, text.into()));
+        }
+        let calibrate = match args.value("calibrate") {
+            Some(rates) => {
+                let invalid = || format!("Invalid rates, expected START:END between 0 and 1: {}", rates);
+                let (start, end) = rates.split_once(':').ok_or_else(invalid)?;
+                let start = start.parse::<f64>().map_err(|_| invalid())?;
+                let end = end.parse::<f64>().map_err(|_| invalid())?;
+                if !(start > 0.0 && start < 1.0 && end > 0.0 && end < 1.0) {
+                    return Err(invalid());
+                }
+                Some((start, end))
+            }
+            None => None,
+        };
+        let exchange_cycle = args.get("exchange-cycle", 10)?;
+        if exchange_cycle == 0 {
+            return Err("Invalid value for option --exchange-cycle: 0".into());
+        }
+        let tempering = args.get("tempering", 0)?;
+        let genetic = args.get("genetic", 0)?;
+        if tempering > 0 && genetic > 0 {
+            return Err("Options --tempering and --genetic cannot be combined".into());
+        }
+        let elites = args.get("elites", 2)?;
+        if genetic > 0 && elites >= genetic {
+            return Err("Invalid value for option --elites: must be below the --genetic population".into());
+        }
+        let transpositions = args.values("transposition").iter().map(|&t| t.into()).collect::<Vec<String>>();
+        let anneal_transpositions = args.get("anneal-transpositions", false)?;
+        if genetic > 0 && (!transpositions.is_empty() || anneal_transpositions) {
+            return Err("Option --genetic cannot be combined with --transposition or --anneal-transpositions".into());
+        }
+        let mutation = args.get("mutation", 0.5)?;
+        if !(0.0..=1.0).contains(&mutation) {
+            return Err("Invalid value for option --mutation: must be between 0 and 1".into());
+        }
+        let share = args.get("share", 0.0)?;
+        if !(0.0..=1.0).contains(&share) {
+            return Err("Invalid value for option --share: must be between 0 and 1".into());
+        }
+        let mut search = args.get("search", Strategy::default())?;
+        if let Some(threshold) = args.optional::<f64>("threshold")? {
+            match &mut search {
+                Strategy::Lavy(lavy) => lavy.threshold = threshold,
+                _ => return Err("Option --threshold needs the lavy search".into()),
+            }
+        }
+        if tempering > 0 && !Tempering::supports(&search) {
+            return Err("Option --tempering needs a search accepting more at higher temperatures: anneal or threshold".into());
+        }
+        let mode = args.get("mode", Mode::Substitution)?;
+        if search.tabu() && matches!(mode, Mode::Transposition(_)) {
+            return Err("Option --search tabu keeps no tabu list for transposition keys, use another search".into());
+        }
+        let temperature_arg = temperature(args, "temperature")?;
+        let end_temperature = temperature(args, "end-temperature")?;
+        if search.temperature() > 0.0 {
+            for (name, value) in [("temperature", temperature_arg), ("end-temperature", end_temperature)].iter() {
+                if *value == Some(0.0) {
+                    return Err(format!("Invalid value for option --{}: must be positive for this search", name));
+                }
+            }
+        }
+        if calibrate.is_some() && (temperature_arg.is_some() || end_temperature.is_some()) {
+            return Err("Option --calibrate cannot be combined with --temperature or --end-temperature".into());
+        }
+        let cooling = args.get("schedule", Cooling::Constant)?;
+        if let (Some(end), Some(rises), false) = (end_temperature, acceptance_rises(&search, &[1.0]), cooling == Cooling::Constant) {
+            let start = temperature_arg.unwrap_or_else(|| search.temperature());
+            if rises && end > start || !rises && end < start {
+                let side = if rises { "below" } else { "above" };
+                return Err(format!("Invalid value for option --end-temperature: must be {} the start temperature {} of this search", side, start));
+            }
+        }
+        let period = args.optional("period")?;
+        if period == Some(0) {
+            return Err("Invalid value for option --period: 0".into());
+        }
+        let restarts = args.get("restarts", 1)?;
+        if restarts == 0 {
+            return Err("Invalid value for option --restarts: 0".into());
+        }
+        let threads = args.get("threads", available_threads())?;
+        if threads == 0 {
+            return Err("Invalid value for option --threads: 0".into());
+        }
+        Ok(Self {
+            mode,
+            period,
+            widths: range(args, "width", "2-12")?,
+            ciphertext: args.positional(0, "CIPHERTEXT")?,
+            cipher_alphabet: alphabet(&args.string("cipher-alphabet", default_cipher_alphabet(mode))),
+            output_alphabet: alphabet(&args.string("output-alphabet", "latin")),
+            order,
+            ngrams: args.string("ngrams", &ngram_path(order)),
+            smoothing: args.get("smoothing", Smoothing::Inverse)?,
+            interpolate,
+            combine: args.get("combine", Combine::Linear)?,
+            letters: args.string("letters", &ngram_path(1)),
+            homophone_ratio,
+            cycle: args.get("cycles", 1000)?,
+            mutate_cycle: args.get("mutate-cycle", 1000)?,
+            derive_cycle: args.get("derive-cycle", 100000)?,
+            cribs,
+            transpositions,
+            anneal_transpositions,
+            search,
+            temperature: temperature_arg,
+            end_temperature,
+            cooling,
+            calibrate,
+            tempering,
+            exchange_cycle,
+            genetic,
+            crossover: args.get("crossover", Crossover::Order)?,
+            tournament: args.get("tournament", 3)?,
+            elites,
+            mutation,
+            seed: args.optional("seed")?,
+            restarts,
+            share,
+            threads,
+            leaderboard: args.get("leaderboard", 5)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Analyze {
+    pub ciphertext: String,
+    pub cipher_alphabet: String,
+    pub letters: String,
+    pub bigrams: String,
+    pub max_period: usize,
+    pub shuffles: usize,
+    pub width: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+impl Analyze {
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        args.check(&["cipher-alphabet", "letters", "bigrams", "max-period", "shuffles", "width", "seed"])?;
+        let width = args.optional("width")?;
+        if width == Some(0) {
+            return Err("Invalid value for option --width: 0".into());
+        }
+        Ok(Self {
+            ciphertext: args.positional(0, "CIPHERTEXT")?,
+            cipher_alphabet: alphabet(&args.string("cipher-alphabet", "zodiac")),
+            letters: args.string("letters", &ngram_path(1)),
+            bigrams: args.string("bigrams", &ngram_path(2)),
+            max_period: args.get("max-period", 40)?,
+            shuffles: args.get("shuffles", 100)?,
+            width,
+            seed: args.optional("seed")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Encrypt {
+    pub mode: Mode,
+    pub key: Option<String>,
+    pub period: usize,
+    pub transposition: Option<String>,
+    pub plaintext: String,
+    pub cipher_alphabet: String,
+    pub output_alphabet: String,
+    pub out: Option<String>,
+    pub seed: Option<u64>,
+}
+
+impl Encrypt {
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        args.check(&[
+            "mode",
+            "key",
+            "period",
+            "transposition",
+            "cipher-alphabet",
+            "output-alphabet",
+            "out",
+            "seed",
+        ])?;
+        let mode = args.get("mode", Mode::Substitution)?;
+        if mode == Mode::Periodic(Variant::General) {
+            return Err("Invalid value for option --mode: periodic".into());
+        }
+        if mode == Mode::Route && args.value("transposition").is_none() {
+            return Err("Mode route needs option --transposition".into());
+        }
+        let period = args.get("period", 6)?;
+        if period == 0 {
+            return Err("Invalid value for option --period: 0".into());
+        }
+        let cipher_alphabet = default_cipher_alphabet(mode);
+        Ok(Self {
+            mode,
+            key: args.value("key").map(|key| key.into()),
+            period,
+            transposition: args.value("transposition").map(|t| t.into()),
+            plaintext: args.positional(0, "PLAINTEXT")?,
+            cipher_alphabet: alphabet(&args.string("cipher-alphabet", cipher_alphabet)),
+            output_alphabet: alphabet(&args.string("output-alphabet", "latin")),
+            out: args.value("out").map(|out| out.into()),
+            seed: args.optional("seed")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildNgrams {
+    pub corpus: String,
+    pub orders: Vec<usize>,
+    pub alphabet: String,
+    pub min_count: u64,
+    pub top: Option<usize>,
+    pub out: String,
+    pub overwrite: bool,
+}
+
+impl BuildNgrams {
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        args.check(&["order", "alphabet", "min-count", "top", "out", "overwrite"])?;
+        let orders = range(args, "order", "4")?;
+        let out = args.string("out", "./data/en_{n}gram.txt");
+        if orders.len() > 1 && !out.contains("{n}") {
+            return Err("Option --out must contain {n} when building several orders".into());
+        }
+        Ok(Self {
+            corpus: args.positional(0, "CORPUS")?,
+            orders,
+            alphabet: alphabet(&args.string("alphabet", "latin")),
+            min_count: args.get("min-count", 1)?,
+            top: args.optional("top")?,
+            out,
+            overwrite: args.get("overwrite", false)?,
+        })
+    }
+
+    pub fn out_path(&self, order: usize) -> String {
+        self.out.replace("{n}", &order.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConvertNgrams {
+    pub grams: String,
+    pub order: usize,
+    pub alphabet: String,
+    pub smoothing: Smoothing,
+    pub out: String,
+}
+
+impl ConvertNgrams {
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        args.check(&["order", "alphabet", "smoothing", "out"])?;
+        let order = args.get("order", 4)?;
+        if order == 0 {
+            return Err("Invalid value for option --order: 0".into());
+        }
+        let grams = args.positional(0, "GRAMS")?;
+        Ok(Self {
+            out: args.string("out", &format!("{}.bin", grams.trim_end_matches(".txt"))),
+            grams,
+            order,
+            alphabet: alphabet(&args.string("alphabet", "latin")),
+            smoothing: args.get("smoothing", Smoothing::Inverse)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Solve(Box<Solve>),
+    Analyze(Analyze),
+    Encrypt(Encrypt),
+    BuildNgrams(BuildNgrams),
+    ConvertNgrams(ConvertNgrams),
+    Help,
+}
+
+impl Command {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let command = match args.next() {
+            Some(command) => command,
+            None => return Ok(Command::Help),
+        };
+        let args = Args::parse(args)?;
+        match command.as_str() {
+            "solve" => Ok(Command::Solve(Box::new(Solve::from_args(&args)?))),
+            "analyze" => Ok(Command::Analyze(Analyze::from_args(&args)?)),
+            "encrypt" => Ok(Command::Encrypt(Encrypt::from_args(&args)?)),
+            "build-ngrams" => Ok(Command::BuildNgrams(BuildNgrams::from_args(&args)?)),
+            "convert-ngrams" => Ok(Command::ConvertNgrams(ConvertNgrams::from_args(&args)?)),
+            "help" | "--help" | "-h" => Ok(Command::Help),
+            _ => Err(format!("Unknown command: {}", command)),
+        }
+    }
+}
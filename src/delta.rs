@@ -0,0 +1,104 @@
+use crate::encoding::{Char, Scorer};
+
+/// Incremental score of a decoded text.
+///
+/// Keeps the score of every n-gram window and the positions of every cipher
+/// symbol, so changing the plaintext of a few symbols only rescores the
+/// windows covering their positions.
+#[derive(Debug, Clone)]
+pub struct Delta {
+    pub positions: Vec<Vec<usize>>,
+    pub windows: Vec<f64>,
+    pub score: f64,
+    previous: f64,
+    marked: Vec<bool>,
+    touched: Vec<usize>,
+    saved: Vec<f64>,
+}
+
+impl Delta {
+    /// Takes the positions of every symbol, see `Cipher::positions`.
+    pub fn new(positions: Vec<Vec<usize>>) -> Self {
+        Self {
+            positions,
+            windows: Vec::new(),
+            score: 0.0,
+            previous: 0.0,
+            marked: Vec::new(),
+            touched: Vec::new(),
+            saved: Vec::new(),
+        }
+    }
+
+    /// Rescores every window of the output.
+    pub fn reset<S: Scorer>(&mut self, scorer: &S, output: &[Char]) -> f64 {
+        let n = scorer.order();
+        let count = (output.len() + 1).saturating_sub(n);
+        self.windows.clear();
+        self.windows.extend((0..count).map(|w| scorer.score_gram(&output[w..w + n])));
+        self.marked.clear();
+        self.marked.resize(count, false);
+        self.touched.clear();
+        self.saved.clear();
+        self.score = self.windows.iter().sum();
+        self.previous = self.score;
+        self.score
+    }
+
+    /// Writes the new plaintext of `(symbol, plaintext)` pairs into the
+    /// output.
+    #[inline(always)]
+    pub fn decode(&self, changed: &[(usize, Char)], output: &mut [Char]) {
+        for &(symbol, plaintext) in changed.iter() {
+            for &position in self.positions[symbol].iter() {
+                output[position] = plaintext;
+            }
+        }
+    }
+
+    /// Rescores the windows covering any position of the changed symbols,
+    /// whose output characters must already be updated. Can be undone once
+    /// with `revert`.
+    pub fn update<S: Scorer>(&mut self, scorer: &S, output: &[Char], changed: &[(usize, Char)]) -> f64 {
+        let n = scorer.order();
+        let count = self.windows.len();
+        self.touched.clear();
+        self.saved.clear();
+        self.previous = self.score;
+        if count == 0 {
+            return self.score;
+        }
+
+        for &(symbol, _) in changed.iter() {
+            for &position in self.positions[symbol].iter() {
+                let first = (position + 1).saturating_sub(n);
+                let last = position.min(count - 1);
+                for window in first..=last {
+                    if !self.marked[window] {
+                        self.marked[window] = true;
+                        self.touched.push(window);
+                    }
+                }
+            }
+        }
+
+        for &window in self.touched.iter() {
+            let score = scorer.score_gram(&output[window..window + n]);
+            self.saved.push(self.windows[window]);
+            self.score += score - self.windows[window];
+            self.windows[window] = score;
+            self.marked[window] = false;
+        }
+        self.score
+    }
+
+    /// Undoes the last `update`.
+    pub fn revert(&mut self) {
+        for (&window, &score) in self.touched.iter().zip(self.saved.iter()) {
+            self.windows[window] = score;
+        }
+        self.touched.clear();
+        self.saved.clear();
+        self.score = self.previous;
+    }
+}
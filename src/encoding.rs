@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use crate::error::{Error, Result};
 use crate::stats::NGrams;
 use crate::stats::Frequency as UnicodeFrequency;
 
@@ -32,9 +33,11 @@ impl Alphabet {
         self.len
     }
 
-    pub fn random_char(&self) -> Char {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn random_char<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Char {
         rng.gen_range(0 as Char..self.len as Char)
     }
 }
@@ -64,26 +67,42 @@ impl Encoding {
     }
 
     #[inline(always)]
-    pub fn alphabet(&self, homophone_ratio: f64) -> Alphabet {
-        if homophone_ratio < 0.0 {
-            panic!();
+    /// The alphabet extended by `homophone_ratio` of its length in homophones.
+    pub fn alphabet(&self, homophone_ratio: f64) -> Result<Alphabet> {
+        if homophone_ratio.is_nan() || homophone_ratio < 0.0 {
+            return Err(Error::Invalid(format!(
+                "Homophone ratio may not be negative: {}",
+                homophone_ratio
+            )));
         }
         let extend_by = (self.encode_map.len() as f64 * homophone_ratio).floor();
-        Alphabet::new(self.encode_map.len() + extend_by as usize)
+        Ok(Alphabet::new(self.encode_map.len() + extend_by as usize))
+    }
+
+    /// The alphabet without homophones.
+    pub fn base_alphabet(&self) -> Alphabet {
+        Alphabet::new(self.encode_map.len())
     }
 
     #[inline(always)]
     pub fn char_set(&self) -> HashSet<char> {
-        self.encode_map.keys().map(|c| *c).collect()
+        self.encode_map.keys().copied().collect()
     }
 
     #[inline(always)]
-    pub fn encode(&self, decoded: &[char], encoded: &mut [Char]) {
+    pub fn encode(&self, decoded: &[char], encoded: &mut [Char]) -> Result<()> {
         for (index, value) in decoded.iter().enumerate() {
-            encoded[index] = *self.encode_map.get(value).expect("Encode error");
+            encoded[index] = *self.encode_map.get(value).ok_or(Error::UnknownSymbol {
+                symbol: *value,
+                position: index,
+            })?;
         }
+        Ok(())
     }
 
+    /// # Panics
+    ///
+    /// On characters outside the alphabet, which `encode` never produces.
     #[inline(always)]
     pub fn decode(&self, encoded: &[Char], decoded: &mut [char]) {
         for (index, value) in encoded.iter().enumerate() {
@@ -91,48 +110,360 @@ impl Encoding {
         }
     }
 
-    pub fn encode_str(&self, decoded: &str) -> Vec<Char> {
+    pub fn encode_str(&self, decoded: &str) -> Result<Vec<Char>> {
         let chars: Vec<char> = decoded.chars().collect();
-        let mut encoded = vec![0; decoded.len()];
-        self.encode(chars.as_slice(), encoded.as_mut_slice());
-        encoded
+        let mut encoded = vec![0; chars.len()];
+        self.encode(chars.as_slice(), encoded.as_mut_slice())?;
+        Ok(encoded)
     }
 
     pub fn decode_str(&self, encoded: &[Char]) -> String {
         let mut chars = vec![0 as char; encoded.len()];
-        self.decode(&encoded, chars.as_mut_slice());
+        self.decode(encoded, chars.as_mut_slice());
         chars.iter().collect()
     }
 }
 
+/// Largest number of entries stored in a dense table, 128MB of scores.
+pub const DENSE_LIMIT: usize = 1 << 24;
+
+/// N-gram score lookup table.
+///
+/// Dense tables are indexed by the n-gram packed as a base `radix` integer
+/// and prefilled with the floor, or the backoff score of every unseen gram.
+/// Sparse tables are used when the dense one would exceed `DENSE_LIMIT`
+/// entries, and score unseen grams with the floor.
+#[derive(Clone, Debug)]
+pub enum Table {
+    Dense(Vec<f64>),
+    Sparse(HashMap<Vec<Char>, f64>),
+}
+
 #[derive(Clone, Debug)]
 pub struct Frequency {
-    pub map: HashMap<Vec<Char>, f64>,
+    pub table: Table,
     pub n: usize,
+    pub radix: usize,
     pub floor: f64,
+    /// Bitset of the packed grams seen in a dense table, whose backoff
+    /// filled entries cannot be told from seen ones by their score.
+    pub seen: Vec<u64>,
 }
 
 impl Frequency {
-    pub fn new(frequency: &UnicodeFrequency, encoding: &Encoding) -> Self {
-        let mut map: HashMap<Vec<Char>, f64> = HashMap::with_capacity(frequency.map.len());
+    pub fn new(frequency: &UnicodeFrequency, encoding: &Encoding) -> Result<Self> {
+        Self::with_limit(frequency, encoding, DENSE_LIMIT)
+    }
+
+    pub fn with_limit(
+        frequency: &UnicodeFrequency,
+        encoding: &Encoding,
+        limit: usize,
+    ) -> Result<Self> {
+        let radix = encoding.encode_map.len();
+        // Unseen grams are filled in below when backing off
+        let fill = match frequency.backoff {
+            Some(_) => f64::NAN,
+            None => frequency.floor,
+        };
+        let mut table = match Self::dense_len(radix, frequency.n) {
+            Some(len) if len <= limit => Table::Dense(vec![fill; len]),
+            _ => Table::Sparse(HashMap::with_capacity(frequency.map.len())),
+        };
+        let mut seen = match &table {
+            Table::Dense(dense) => vec![0; dense.len().div_ceil(64)],
+            Table::Sparse(_) => Vec::new(),
+        };
         for (key, &val) in frequency.map.iter() {
             let decoded = key.chars().collect::<Vec<char>>();
-            let mut encoded = vec![0; key.len()];
-            encoding.encode(&decoded, &mut encoded);
-            map.insert(encoded, val);
+            let mut encoded = vec![0; decoded.len()];
+            encoding.encode(&decoded, &mut encoded)?;
+            match &mut table {
+                Table::Dense(dense) => {
+                    let index = Self::pack(radix, &encoded);
+                    dense[index] = val;
+                    seen[index / 64] |= 1 << (index % 64);
+                }
+                Table::Sparse(sparse) => {
+                    sparse.insert(encoded, val);
+                }
+            }
         }
-        Self {
-            map,
+        if let (Table::Dense(dense), Some(backoff)) = (&mut table, &frequency.backoff) {
+            let lower = Self::with_limit(&backoff.lower, encoding, limit)?;
+            let mut weights = vec![0.0; dense.len() / radix];
+            for (context, &weight) in backoff.weights.iter() {
+                let decoded = context.chars().collect::<Vec<char>>();
+                let mut encoded = vec![0; decoded.len()];
+                encoding.encode(&decoded, &mut encoded)?;
+                weights[Self::pack(radix, &encoded)] = weight;
+            }
+            let modulus = dense.len() / radix;
+            for (index, score) in dense.iter_mut().enumerate() {
+                if score.is_nan() {
+                    *score = weights[index / radix] + lower.get_packed(index % modulus);
+                }
+            }
+        }
+        Ok(Self {
+            table,
             n: frequency.n,
+            radix,
             floor: frequency.floor,
+            seen,
+        })
+    }
+
+    fn dense_len(radix: usize, n: usize) -> Option<usize> {
+        let mut len = 1usize;
+        for _ in 0..n {
+            len = len.checked_mul(radix)?;
+        }
+        Some(len)
+    }
+
+    #[inline(always)]
+    fn pack(radix: usize, gram: &[Char]) -> usize {
+        let mut index = 0;
+        for &c in gram.iter() {
+            index = index * radix + c as usize;
+        }
+        index
+    }
+
+    #[inline(always)]
+    fn get_packed(&self, index: usize) -> f64 {
+        match &self.table {
+            Table::Dense(dense) => dense[index],
+            Table::Sparse(_) => self.floor,
+        }
+    }
+
+    #[inline(always)]
+    fn contains_packed(&self, index: usize) -> bool {
+        self.seen.get(index / 64).is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    /// Whether the gram was seen, rather than scored with the floor or by
+    /// backing off to a lower order.
+    pub fn contains(&self, gram: &[Char]) -> bool {
+        match &self.table {
+            Table::Dense(_) => self.contains_packed(Self::pack(self.radix, gram)),
+            Table::Sparse(sparse) => sparse.contains_key(gram),
+        }
+    }
+
+    pub fn is_dense(&self) -> bool {
+        matches!(self.table, Table::Dense(_))
+    }
+
+    #[inline(always)]
+    pub fn get(&self, gram: &[Char]) -> f64 {
+        match &self.table {
+            Table::Dense(dense) => dense[Self::pack(self.radix, gram)],
+            Table::Sparse(sparse) => *sparse.get(gram).unwrap_or(&self.floor),
+        }
+    }
+}
+
+impl Scorer for Frequency {
+    #[inline(always)]
+    fn order(&self) -> usize {
+        self.n
+    }
+
+    #[inline(always)]
+    fn score_gram(&self, gram: &[Char]) -> f64 {
+        self.get(gram)
+    }
+
+    fn score(&self, glyphs: &[Char]) -> f64 {
+        let dense = match &self.table {
+            Table::Dense(dense) => dense,
+            Table::Sparse(_) => return NGrams::new(glyphs, self.n).map(|g| self.get(g)).sum(),
+        };
+        if glyphs.len() < self.n {
+            return 0.0;
+        }
+        // Roll the packed index along instead of repacking every window
+        let modulus = dense.len() / self.radix;
+        let mut index = Self::pack(self.radix, &glyphs[..self.n - 1]);
+        let mut score = 0.0;
+        for &c in glyphs[self.n - 1..].iter() {
+            index = (index % modulus) * self.radix + c as usize;
+            score += dense[index];
         }
+        score
     }
+}
+
+/// Sums per-window scores over a text, one window per n-gram.
+pub trait Scorer {
+    /// Number of characters in a single window.
+    fn order(&self) -> usize;
 
-    pub fn score(&self, glyphs: &[Char]) -> f64 {
+    /// Scores a single window of exactly `order()` characters.
+    fn score_gram(&self, gram: &[Char]) -> f64;
+
+    fn score(&self, glyphs: &[Char]) -> f64 {
         let mut score = 0.0;
-        for gram in NGrams::new(glyphs, self.n) {
-            score += self.map.get(gram).unwrap_or(&self.floor);
+        for gram in NGrams::new(glyphs, self.order()) {
+            score += self.score_gram(gram);
         }
         score
     }
 }
+
+impl<S: Scorer + ?Sized> Scorer for &S {
+    #[inline(always)]
+    fn order(&self) -> usize {
+        (**self).order()
+    }
+
+    #[inline(always)]
+    fn score_gram(&self, gram: &[Char]) -> f64 {
+        (**self).score_gram(gram)
+    }
+
+    fn score(&self, glyphs: &[Char]) -> f64 {
+        (**self).score(glyphs)
+    }
+}
+
+/// How the orders of an `Interpolated` model are combined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Combine {
+    /// Weighted sum of the scores of every order.
+    Linear,
+    /// Log10 of the weighted sum of probabilities, for log10 scores.
+    Probability,
+    /// Score of the highest order that saw the gram, adding the given log10
+    /// penalty for every order backed off.
+    Backoff(f64),
+}
+
+impl std::str::FromStr for Combine {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("backoff", penalty)) => penalty
+                .parse()
+                .map(Combine::Backoff)
+                .map_err(|_| format!("Invalid penalty: {}", penalty)),
+            None if s == "backoff" => Ok(Combine::Backoff(0.4f64.log10())),
+            None if s == "linear" => Ok(Combine::Linear),
+            None if s == "probability" => Ok(Combine::Probability),
+            _ => Err(format!("Unknown combination: {}", s)),
+        }
+    }
+}
+
+/// Combines n-gram models of several orders into a single scorer.
+///
+/// Every window of the highest order is scored by all models on its
+/// suffix of their order. When the highest order table is dense the
+/// combined scores are precomputed into a table of the same shape.
+#[derive(Clone, Debug)]
+pub struct Interpolated {
+    /// Models with their weights, ordered by ascending order.
+    pub models: Vec<(Frequency, f64)>,
+    pub combine: Combine,
+    combined: Option<Frequency>,
+}
+
+impl Interpolated {
+    pub fn new(mut models: Vec<(Frequency, f64)>, combine: Combine) -> Result<Self> {
+        if models.is_empty() {
+            return Err(Error::Invalid("Interpolated: no models given".into()));
+        }
+        models.sort_by_key(|(model, _)| model.n);
+        let mut interpolated = Self {
+            models,
+            combine,
+            combined: None,
+        };
+        interpolated.combined = interpolated.precompute();
+        Ok(interpolated)
+    }
+
+    fn top(&self) -> &Frequency {
+        &self.models[self.models.len() - 1].0
+    }
+
+    fn precompute(&self) -> Option<Frequency> {
+        let top = self.top();
+        let len = match &top.table {
+            Table::Dense(dense) => dense.len(),
+            Table::Sparse(_) => return None,
+        };
+        if self.models.iter().any(|(model, _)| !model.is_dense() || model.radix != top.radix) {
+            return None;
+        }
+        let mut dense = vec![0.0; len];
+        let mut scores = vec![0.0; self.models.len()];
+        let mut seen = vec![false; self.models.len()];
+        for (index, combined) in dense.iter_mut().enumerate() {
+            for (k, (model, _)) in self.models.iter().enumerate() {
+                let modulus = top.radix.pow(model.n as u32);
+                scores[k] = model.get_packed(index % modulus);
+                seen[k] = model.contains_packed(index % modulus);
+            }
+            *combined = self.combine_scores(&scores, &seen);
+        }
+        let floors = self.models.iter().map(|(m, _)| m.floor).collect::<Vec<_>>();
+        Some(Frequency {
+            table: Table::Dense(dense),
+            n: top.n,
+            radix: top.radix,
+            floor: self.combine_scores(&floors, &vec![false; floors.len()]),
+            seen: top.seen.clone(),
+        })
+    }
+
+    /// Combines the scores of every order, `seen` telling which orders saw
+    /// the gram.
+    fn combine_scores(&self, scores: &[f64], seen: &[bool]) -> f64 {
+        match self.combine {
+            Combine::Linear => self.models.iter().zip(scores).map(|((_, w), s)| w * s).sum(),
+            Combine::Probability => {
+                let p: f64 = self.models.iter().zip(scores).map(|((_, w), s)| w * 10f64.powf(*s)).sum();
+                p.log10()
+            }
+            Combine::Backoff(penalty) => {
+                let mut backed_off = 0.0;
+                for k in (0..self.models.len()).rev() {
+                    if seen[k] || k == 0 {
+                        return scores[k] + backed_off;
+                    }
+                    backed_off += penalty;
+                }
+                unreachable!()
+            }
+        }
+    }
+}
+
+impl Scorer for Interpolated {
+    #[inline(always)]
+    fn order(&self) -> usize {
+        self.top().n
+    }
+
+    #[inline(always)]
+    fn score_gram(&self, gram: &[Char]) -> f64 {
+        if let Some(combined) = &self.combined {
+            return combined.get(gram);
+        }
+        let suffixes = self.models.iter().map(|(model, _)| (model, &gram[gram.len() - model.n..]));
+        let scores = suffixes.clone().map(|(model, suffix)| model.get(suffix)).collect::<Vec<_>>();
+        let seen = suffixes.map(|(model, suffix)| model.contains(suffix)).collect::<Vec<_>>();
+        self.combine_scores(&scores, &seen)
+    }
+
+    fn score(&self, glyphs: &[Char]) -> f64 {
+        match &self.combined {
+            Some(combined) => combined.score(glyphs),
+            None => NGrams::new(glyphs, self.order()).map(|g| self.score_gram(g)).sum(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt;
+
+/// Errors of the library, with the file, line and symbol they concern.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io { path: String, source: std::io::Error },
+    /// A file is not valid UTF-8.
+    Utf8 { path: String },
+    /// A line of a gram or dictionary file could not be parsed. Lines count
+    /// from 1.
+    Parse { path: String, line: usize, message: String },
+    /// A symbol outside the alphabet, at a character position of the
+    /// encoded text. Files report their symbols as `Parse` errors instead.
+    UnknownSymbol { symbol: char, position: usize },
+    /// An argument outside its valid range.
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(path: &str, source: std::io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn parse(path: &str, line: usize, message: impl Into<String>) -> Self {
+        Error::Parse {
+            path: path.into(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path, source),
+            Error::Utf8 { path } => write!(f, "{}: invalid UTF-8", path),
+            Error::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path, line, message),
+            Error::UnknownSymbol { symbol, position } => write!(f, "Unknown symbol {:?} at position {}", symbol, position),
+            Error::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
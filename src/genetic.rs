@@ -0,0 +1,211 @@
+use crate::ciphers::{Cipher, Substitution};
+use crate::encoding::Scorer;
+use crate::hill::{Climber, Config};
+use crate::key::Key;
+use crate::parallel::{Leaderboard, Solution};
+use crate::util::{self, Random};
+use rand::Rng;
+
+/// How two parent keys combine into a child.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossover {
+    /// Takes every entry from either parent, see `Key::splice`.
+    Uniform,
+    /// Keeps a slice of the first parent, see `Key::order_crossover`.
+    Order,
+    /// Keeps random entries of the first parent, see `Key::position_crossover`.
+    Position,
+}
+
+/// Parses `uniform`, `order` or `position`.
+impl std::str::FromStr for Crossover {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Crossover::Uniform),
+            "order" => Ok(Crossover::Order),
+            "position" => Ok(Crossover::Position),
+            _ => Err(format!("Unknown crossover: {}", s)),
+        }
+    }
+}
+
+impl Crossover {
+    /// Crosses `key` with `other` in place.
+    pub fn apply<R: Rng + ?Sized>(self, key: &mut Key, other: &Key, rng: &mut R) {
+        match self {
+            Crossover::Uniform => key.splice(other, rng),
+            Crossover::Order => key.order_crossover(other, rng),
+            Crossover::Position => key.position_crossover(other, rng),
+        }
+    }
+}
+
+/// A key of the population and the energy of its output.
+#[derive(Debug, Clone)]
+pub struct Individual {
+    pub key: Key,
+    pub energy: f64,
+}
+
+/// Generations between computations of the quadratic `diversity`.
+pub const DIVERSITY_INTERVAL: usize = 10;
+
+/// Energies and diversity of a generation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Generation {
+    pub index: usize,
+    pub best: f64,
+    pub mean: f64,
+    /// See `diversity`, computed every `DIVERSITY_INTERVAL` generations and
+    /// at the last one, otherwise the latest value.
+    pub diversity: f64,
+}
+
+/// Mean share of the entries in which two keys of the population differ.
+pub fn diversity(population: &[Individual]) -> f64 {
+    let mut pairs = 0;
+    let mut differing = 0.0;
+    for (index, a) in population.iter().enumerate() {
+        for b in population[index + 1..].iter() {
+            let len = a.key.len().max(1);
+            let different = (0..a.key.len()).filter(|&i| a.key[i] != b.key[i]).count();
+            differing += different as f64 / len as f64;
+            pairs += 1;
+        }
+    }
+    differing / pairs.max(1) as f64
+}
+
+/// Evolves a population of substitution keys instead of climbing one:
+/// every generation keeps the best `elites` keys and breeds the others from
+/// parents picked by tournament, crossed and mutated by a random swap, or
+/// for homophonic keys a random change of the last symbols.
+#[derive(Debug, Clone)]
+pub struct Genetic {
+    pub population: usize,
+    pub generations: usize,
+    /// Keys drawn for every tournament, the best one is the parent.
+    pub tournament: usize,
+    pub elites: usize,
+    pub crossover: Crossover,
+    /// Probability that a child is crossed with a second parent.
+    pub crossover_rate: f64,
+    /// Probability that a child is mutated.
+    pub mutation_rate: f64,
+    pub seed: u64,
+    pub leaderboard: usize,
+}
+
+impl Default for Genetic {
+    fn default() -> Self {
+        Self {
+            population: 100,
+            generations: 1000,
+            tournament: 3,
+            elites: 2,
+            crossover: Crossover::Order,
+            crossover_rate: 0.9,
+            mutation_rate: 0.5,
+            seed: 0,
+            leaderboard: 5,
+        }
+    }
+}
+
+impl Genetic {
+    /// Draws the first population with `config.random_key` and breeds all
+    /// generations, calling `generation` after each. Returns the best key of
+    /// every generation, the restart of a solution being its generation.
+    pub fn run<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB, GENERATION>(
+        &self,
+        climber: &Climber<Substitution>,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        mut generation: GENERATION,
+    ) -> Leaderboard<Key>
+    where
+        SCORER: Scorer,
+        ENERGY: Fn(f64, usize) -> f64,
+        RANDOMKEY: Fn(&mut Climber<Substitution>, &mut Random),
+        GENERATION: FnMut(&Generation, &[Individual]),
+    {
+        let mut rng = util::seeded_random(self.seed);
+        let mut climber = climber.clone();
+        let evaluate = |climber: &mut Climber<Substitution>, key: Key| {
+            climber.run_key = key;
+            let energy = (config.energy)(climber.decode(&config.scorer), climber.output_buf.len());
+            Individual {
+                key: climber.run_key.clone(),
+                energy,
+            }
+        };
+        let by_energy = |a: &Individual, b: &Individual| b.energy.total_cmp(&a.energy);
+
+        let size = self.population.max(2);
+        let mut population = Vec::with_capacity(size);
+        for _ in 0..size {
+            (config.random_key)(&mut climber, &mut rng);
+            let mut key = climber.run_key.clone();
+            climber.cipher.constrain(&mut key);
+            population.push(evaluate(&mut climber, key));
+        }
+        population.sort_by(by_energy);
+
+        let homophonic = climber.cipher.cipher_alphabet.len() > climber.cipher.output_alphabet.len();
+        let mut leaderboard = Leaderboard::new(self.leaderboard);
+        let mut last_diversity = 0.0;
+        for index in 0..self.generations {
+            let mut next = population[..self.elites.min(size)].to_vec();
+            while next.len() < size {
+                let mut key = self.select(&population, &mut rng).key.clone();
+                if util::probability(self.crossover_rate, &mut rng) {
+                    let other = self.select(&population, &mut rng);
+                    self.crossover.apply(&mut key, &other.key, &mut rng);
+                }
+                if util::probability(self.mutation_rate, &mut rng) {
+                    if homophonic && util::probability(0.5, &mut rng) {
+                        key.random_putc(&climber.cipher.output_alphabet, &mut rng);
+                    } else {
+                        key.random_swap(&climber.cipher.crib, &mut rng);
+                    }
+                }
+                climber.cipher.constrain(&mut key);
+                next.push(evaluate(&mut climber, key));
+            }
+            next.sort_by(by_energy);
+            population = next;
+
+            let best = &population[0];
+            evaluate(&mut climber, best.key.clone());
+            leaderboard.offer(Solution {
+                restart: index,
+                energy: best.energy,
+                transposition: climber.run_transposition,
+                key: best.key.clone(),
+                output: climber.output_buf.clone(),
+            });
+            let mean = population.iter().map(|i| i.energy).sum::<f64>() / size as f64;
+            if index % DIVERSITY_INTERVAL == 0 || index + 1 == self.generations {
+                last_diversity = diversity(&population);
+            }
+            let stats = Generation {
+                index,
+                best: best.energy,
+                mean,
+                diversity: last_diversity,
+            };
+            generation(&stats, &population);
+        }
+        leaderboard
+    }
+
+    /// Picks the best of `tournament` random keys of a population sorted
+    /// by descending energy.
+    fn select<'a>(&self, population: &'a [Individual], rng: &mut Random) -> &'a Individual {
+        let index = (0..self.tournament.max(1))
+            .map(|_| rng.gen_range(0..population.len()))
+            .min()
+            .unwrap_or(0);
+        &population[index]
+    }
+}
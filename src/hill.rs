@@ -1,23 +1,37 @@
-use crate::encoding::{Encoding, Char, Alphabet};
-use crate::key::{Crib, Key};
-
-const MIN_ENERGY: f64 = -99e99;
-
-pub struct Config<REPORT, ENERGY, ACCEPT, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>
-where
-    REPORT: Fn(&Climber, usize, usize, usize),
-    ENERGY: Fn(&[Char]) -> f64,
-    ACCEPT: Fn(f64, f64) -> bool,
-    RANDOMKEY: Fn(&mut Climber),
-    DERIVEKEY: Fn(&mut Climber),
-    MUTATEKEY: Fn(&mut Climber),
-    CRIB: Fn(&mut Climber),
-{
+use crate::ciphers::{Cipher, Substitution};
+use crate::delta::Delta;
+use crate::encoding::{Encoding, Char, Scorer};
+use crate::error::{Error, Result};
+use crate::parallel::SharedBest;
+use crate::schedule::{Cooling, Schedule};
+use crate::search::Search;
+use crate::transposition::Transposition;
+use crate::util::Random;
+use rand::Rng;
+use std::sync::Arc;
+
+pub const MIN_ENERGY: f64 = -99e99;
+
+/// Moves sampled from each random key by `sample_losses`.
+const SAMPLES_PER_KEY: usize = 100;
+
+/// Random moves sampled by `Climber::calibrate`.
+const CALIBRATION_SAMPLES: usize = 2000;
+
+/// Callbacks and limits of a climb. The callbacks receive the climber, so
+/// their argument types must be written out where the config is built.
+pub struct Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB> {
     pub cycle: usize,
     pub derive_cycle: usize,
     pub mutate_cycle: usize,
+    /// Scores the decoded output, incrementally while swapping.
+    pub scorer: SCORER,
+    /// Maps the score and length of the output to the climbed energy.
     pub energy: ENERGY,
-    pub accept: ACCEPT,
+    /// Decides which moves are taken, cloned for every climb.
+    pub search: SEARCH,
+    /// Temperature passed to the search, updated after every cycle.
+    pub schedule: Schedule,
     pub random_key: RANDOMKEY,
     pub derive_key: DERIVEKEY,
     pub mutate_key: MUTATEKEY,
@@ -25,120 +39,303 @@ where
     pub crib: CRIB,
 }
 
+/// Climbs the keys of a cipher family, moving between neighbouring keys.
 #[derive(Debug, Clone)]
-pub struct Climber {
+pub struct Climber<C: Cipher> {
+    pub cipher: C,
     pub cipher_encoding: Encoding,
-    pub cipher_alphabet: Alphabet,
     pub output_encoding: Encoding,
-    pub output_alphabet: Alphabet,
+    /// Ciphertext in its original order.
+    pub source_buf: Vec<Char>,
+    /// Ciphertext reordered by the running transposition.
     pub cipher_buf: Vec<Char>,
     pub output_buf: Vec<Char>,
-    pub top_key: Key,
-    pub run_key: Key,
-    pub fix_key: Key,
+    /// Candidate transpositions undone before decoding, switched between
+    /// while climbing when there are several.
+    pub transpositions: Vec<Transposition>,
+    pub run_transposition: usize,
+    pub top_transposition: usize,
+    pub top_key: C::Key,
+    pub run_key: C::Key,
     pub run_energy: f64,
     pub top_energy: f64,
-    pub crib: Crib,
+    /// Temperature of the running cycle, set by the schedule.
+    pub temperature: f64,
+    pub delta: Delta,
+    /// Best key of all the workers of a `Parallel` run, offered every new
+    /// top key.
+    pub shared: Option<Arc<SharedBest<C::Key>>>,
+    /// Symbols changed by the last move, with their plaintext.
+    changed: Vec<(usize, Char)>,
 }
 
-impl Climber {
+impl Climber<Substitution> {
     pub fn new(
         cipher_buf: Vec<Char>,
         cipher_encoding: Encoding,
         output_encoding: Encoding,
         homophone_ratio: f64,
-    ) -> Self {
-        let output_alphabet = output_encoding.alphabet(0.0);
-        let cipher_alphabet = cipher_encoding.alphabet(homophone_ratio);
-        let fix_key = Key::new(cipher_alphabet.len());
-        let top_key = Key::new(cipher_alphabet.len());
-        let run_key = Key::new(cipher_alphabet.len());
-        let output_buf = cipher_buf.clone();
-        println!("Key length: {}", run_key.len());
-        let crib = Crib::new(run_key.len());
+    ) -> Result<Self> {
+        let cipher_alphabet = cipher_encoding.alphabet(homophone_ratio)?;
+        let cipher = Substitution::new(cipher_alphabet, output_encoding.clone());
+        Ok(Self::with_cipher(cipher, cipher_buf, cipher_encoding, output_encoding))
+    }
+
+    pub fn crib_char(&mut self, encoded_char: Char, decoded_char: Char) {
+        self.cipher.crib_char(encoded_char, decoded_char);
+    }
+
+    pub fn crib_char_at(&mut self, index: usize, decoded_char: Char) {
+        let encoded_char = self.cipher_buf[index];
+        self.crib_char(encoded_char, decoded_char);
+    }
+
+    pub fn crib_str(&mut self, offset: usize, decoded: &str) -> Result<()> {
+        let crib = self.output_encoding.encode_str(decoded)?;
+        for (i, &c) in crib.iter().enumerate() {
+            self.crib_char_at(offset + i, c);
+        }
+        Ok(())
+    }
+}
+
+impl<C: Cipher> Climber<C> {
+    pub fn with_cipher(cipher: C, cipher_buf: Vec<Char>, cipher_encoding: Encoding, output_encoding: Encoding) -> Self {
+        // Placeholder keys, every climb starts from `random_key`
+        let mut rng = crate::util::seeded_random(0);
+        let run_key = cipher.random_key(&mut rng);
+        let delta = Delta::new(cipher.positions(&cipher_buf));
         Self {
+            top_key: run_key.clone(),
+            run_key,
+            cipher,
             cipher_encoding,
             output_encoding,
-            cipher_alphabet,
-            output_alphabet,
+            source_buf: cipher_buf.clone(),
+            output_buf: cipher_buf.clone(),
             cipher_buf,
-            output_buf,
-            fix_key,
-            run_key,
-            top_key,
+            transpositions: Vec::new(),
+            run_transposition: 0,
+            top_transposition: 0,
             run_energy: MIN_ENERGY,
             top_energy: MIN_ENERGY,
-            crib,
+            temperature: 0.0,
+            delta,
+            shared: None,
+            changed: Vec::new(),
         }
     }
 
-    pub fn crib_char(&mut self, encoded_char: Char, decoded_char: Char) {
-        self.fix_key[encoded_char as usize] = decoded_char;
-        self.crib.fix(encoded_char as usize);
+    /// Sets the candidate transpositions and applies the first one. Cribs
+    /// placed afterwards refer to positions of its output. Fails when a
+    /// transposition does not match the length of the ciphertext.
+    pub fn set_transpositions(&mut self, transpositions: Vec<Transposition>) -> Result<()> {
+        if transpositions.iter().any(|t| t.len() != self.source_buf.len()) {
+            return Err(Error::Invalid(format!(
+                "Transpositions must match the ciphertext length {}",
+                self.source_buf.len()
+            )));
+        }
+        self.transpositions = transpositions;
+        self.top_transposition = 0;
+        self.transpose(0);
+        Ok(())
     }
 
-    pub fn crib_char_at(&mut self, index: usize, decoded_char: Char) {
-        let encoded_char = self.cipher_buf[index];
-        self.crib_char(encoded_char, decoded_char);
+    /// Reorders the ciphertext by the candidate transposition at `index`.
+    pub fn transpose(&mut self, index: usize) {
+        self.run_transposition = index;
+        match self.transpositions.get(index) {
+            Some(transposition) => transposition.apply_to(&self.source_buf, &mut self.cipher_buf),
+            None => self.cipher_buf.copy_from_slice(&self.source_buf),
+        }
+        self.delta = Delta::new(self.cipher.positions(&self.cipher_buf));
     }
 
-    pub fn crib_str(&mut self, offset: usize, decoded: &str) {
-        let crib = self.output_encoding.encode_str(decoded);
-        for (i, &c) in crib.iter().enumerate() {
-            self.crib_char_at(offset + i, c);
+    /// Decodes the best key and transposition into the output.
+    pub fn decode_top(&mut self) {
+        self.transpose(self.top_transposition);
+        self.cipher.decrypt(&self.top_key, &self.cipher_buf, &mut self.output_buf);
+    }
+
+    /// Decodes the whole output with the running key and rescores it.
+    pub fn decode<S: Scorer>(&mut self, scorer: &S) -> f64 {
+        self.cipher.decrypt(&self.run_key, &self.cipher_buf, &mut self.output_buf);
+        self.delta.reset(scorer, &self.output_buf)
+    }
+
+    /// Applies a move to the running key and rescores the output, only
+    /// around the changed symbols when the cipher reports them. Returns
+    /// the move undoing it.
+    #[inline(always)]
+    fn step<S: Scorer>(&mut self, scorer: &S, step: C::Move) -> (C::Move, f64) {
+        let undo = self.cipher.apply(&mut self.run_key, step);
+        self.changed.clear();
+        if !self.cipher.changed(&self.run_key, step, &mut self.changed) {
+            return (undo, self.decode(scorer));
         }
+        self.delta.decode(&self.changed, &mut self.output_buf);
+        (undo, self.delta.update(scorer, &self.output_buf, &self.changed))
+    }
+
+    /// Undoes the last `step`.
+    #[inline(always)]
+    fn undo<S: Scorer>(&mut self, scorer: &S, undo: C::Move) {
+        self.cipher.apply(&mut self.run_key, undo);
+        self.changed.clear();
+        if !self.cipher.changed(&self.run_key, undo, &mut self.changed) {
+            self.decode(scorer);
+            return;
+        }
+        self.delta.decode(&self.changed, &mut self.output_buf);
+        self.delta.revert();
+    }
+
+    /// Climbs `config.cycle` cycles from a random key.
+    pub fn climb<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>(
+        &mut self,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        rng: &mut Random,
+    ) where
+        SCORER: Scorer,
+        REPORT: Fn(&Climber<C>, usize, usize, usize),
+        ENERGY: Fn(f64, usize) -> f64,
+        SEARCH: Search,
+        RANDOMKEY: Fn(&mut Climber<C>, &mut Random),
+        DERIVEKEY: Fn(&mut Climber<C>, &mut Random),
+        MUTATEKEY: Fn(&mut Climber<C>, &mut Random),
+        CRIB: Fn(&mut Climber<C>),
+    {
+        self.randomize(config, rng);
+        self.resume(config, config.schedule, rng);
     }
 
-    pub fn climb<REPORT, ENERGY, ACCEPT, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>(
+    /// Starts from a random key, and a random candidate transposition when
+    /// there are several.
+    pub fn randomize<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>(
         &mut self,
-        config: &Config<REPORT, ENERGY, ACCEPT, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        rng: &mut Random,
     ) where
-        REPORT: Fn(&Climber, usize, usize, usize),
-        ENERGY: Fn(&[Char]) -> f64,
-        ACCEPT: Fn(f64, f64) -> bool,
-        RANDOMKEY: Fn(&mut Climber),
-        DERIVEKEY: Fn(&mut Climber),
-        MUTATEKEY: Fn(&mut Climber),
-        CRIB: Fn(&mut Climber),
+        RANDOMKEY: Fn(&mut Climber<C>, &mut Random),
     {
-        (config.random_key)(self);
+        (config.random_key)(self, rng);
+        if self.transpositions.len() > 1 {
+            self.transpose(rng.gen_range(0..self.transpositions.len()));
+        }
+        self.cipher.constrain(&mut self.run_key);
+    }
 
-        for &index in self.crib.fixed.iter() {
-            self.run_key[index] = self.fix_key[index];
+    /// Climbs `config.cycle` cycles from the running key, at the
+    /// temperatures of `schedule` instead of the config's.
+    pub fn resume<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>(
+        &mut self,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        schedule: Schedule,
+        rng: &mut Random,
+    ) where
+        SCORER: Scorer,
+        REPORT: Fn(&Climber<C>, usize, usize, usize),
+        ENERGY: Fn(f64, usize) -> f64,
+        SEARCH: Search,
+        RANDOMKEY: Fn(&mut Climber<C>, &mut Random),
+        DERIVEKEY: Fn(&mut Climber<C>, &mut Random),
+        MUTATEKEY: Fn(&mut Climber<C>, &mut Random),
+        CRIB: Fn(&mut Climber<C>),
+    {
+        self.run_energy = (config.energy)(self.decode(&config.scorer), self.output_buf.len());
+        if self.run_energy > self.top_energy {
+            self.keep_top(config);
         }
-        self.run_key.decode(&self.cipher_buf, &mut self.output_buf);
+        let mut search = config.search.clone();
+        search.start(self.run_energy);
+        let mut schedule = schedule;
+        schedule.reset();
+        self.temperature = schedule.temperature();
 
         let mut cycle = 0;
         let mut mutate_cycle = 0;
         let mut derive_cycle = 0;
         let mut accepted = 1;
         let mut rejected = 1;
+        let tabu = search.tabu();
+        let mut assignments = Vec::new();
         while cycle < config.cycle {
-            (config.report)(&self, cycle, accepted, rejected);
-            for ii in 0..self.crib.loose.len() {
-                for jj in ii+1..self.crib.loose.len() {
-
-                    if ii >= self.crib.loose.len() || jj >= self.crib.loose.len() {
-                        break;
+            (config.report)(self, cycle, accepted, rejected);
+            let (cycle_accepted, cycle_rejected, cycle_top) = (accepted, rejected, self.top_energy);
+            let moves = self.cipher.moves(&self.run_key);
+            if search.steepest() {
+                // One move per round, the best of the neighbourhood
+                if derive_cycle >= config.derive_cycle {
+                    (config.derive_key)(self, rng);
+                    self.run_energy = (config.energy)(self.decode(&config.scorer), self.output_buf.len());
+                    derive_cycle = 0;
+                }
+                if mutate_cycle >= config.mutate_cycle {
+                    (config.mutate_key)(self, rng);
+                    self.run_energy = (config.energy)(self.decode(&config.scorer), self.output_buf.len());
+                    mutate_cycle = 0;
+                }
+                let mut best: Option<(C::Move, f64)> = None;
+                for &step in moves.iter() {
+                    let (undo, score) = self.step(&config.scorer, step);
+                    let energy = (config.energy)(score, self.output_buf.len());
+                    if tabu {
+                        assignments.clear();
+                        self.cipher.assignments(&self.run_key, step, &mut assignments);
                     }
-
-                    let (i, j) = (self.crib.loose[ii], self.crib.loose[jj]);
-
-                    if derive_cycle == config.derive_cycle {
-                        (config.derive_key)(self);
+                    self.undo(&config.scorer, undo);
+                    if tabu && !search.allowed(&assignments, energy) {
+                        continue;
+                    }
+                    if best.is_none_or(|(_, e)| energy > e) {
+                        best = Some((step, energy));
+                    }
+                }
+                match best {
+                    Some((step, energy)) if search.accept(self.run_energy, energy, self.temperature, rng) => {
+                        if tabu {
+                            assignments.clear();
+                            self.cipher.assignments(&self.run_key, step, &mut assignments);
+                            search.moved(&assignments);
+                        }
+                        // No neighbour improved, as when the round is rejected
+                        if energy <= self.run_energy {
+                            mutate_cycle += moves.len();
+                            derive_cycle += moves.len();
+                        }
+                        self.step(&config.scorer, step);
+                        accepted += 1;
+                        self.run_energy = energy;
+                    }
+                    _ => {
+                        mutate_cycle += moves.len();
+                        derive_cycle += moves.len();
+                        rejected += 1;
+                    }
+                }
+                if self.run_energy > self.top_energy {
+                    self.keep_top(config);
+                    mutate_cycle = 0;
+                    derive_cycle = 0;
+                }
+            } else {
+                for step in moves {
+                    if derive_cycle >= config.derive_cycle {
+                        (config.derive_key)(self, rng);
+                        self.decode(&config.scorer);
                         derive_cycle = 0;
                     }
-                    if mutate_cycle == config.mutate_cycle {
-                        (config.mutate_key)(self);
+                    if mutate_cycle >= config.mutate_cycle {
+                        (config.mutate_key)(self, rng);
+                        self.decode(&config.scorer);
                         mutate_cycle = 0;
                     }
 
-                    self.run_key.swap(i, j);
-                    self.run_key.decode(&self.cipher_buf, &mut self.output_buf);
-
-                    let energy = (config.energy)(&self.output_buf);
-                    if !(config.accept)(self.run_energy, energy) {
-                        self.run_key.swap(i, j);
+                    let (undo, score) = self.step(&config.scorer, step);
+                    let energy = (config.energy)(score, self.output_buf.len());
+                    if !search.accept(self.run_energy, energy, self.temperature, rng) {
+                        self.undo(&config.scorer, undo);
                         mutate_cycle += 1;
                         derive_cycle += 1;
                         rejected += 1;
@@ -148,15 +345,121 @@ impl Climber {
 
                     self.run_energy = energy;
                     if self.run_energy > self.top_energy {
-                        self.top_key.copy(&self.run_key);
-                        self.top_energy = self.run_energy;
-                        (config.crib)(self);
+                        self.keep_top(config);
                         mutate_cycle = 0;
                         derive_cycle = 0;
                     }
                 }
             }
+            if self.transpositions.len() > 1 {
+                self.switch_transposition(config, &mut search, rng);
+            }
+            let tried = (accepted - cycle_accepted + rejected - cycle_rejected).max(1);
+            let acceptance = (accepted - cycle_accepted) as f64 / tried as f64;
+            schedule.update(cycle, config.cycle, acceptance, self.top_energy > cycle_top);
+            self.temperature = schedule.temperature();
             cycle += 1;
         }
     }
+
+    /// Energy losses of `samples` random moves from random keys, skipping
+    /// the moves that do not lose energy. Used to calibrate temperatures,
+    /// the running key is kept.
+    pub fn sample_losses<S, E>(&mut self, scorer: &S, energy: &E, samples: usize, rng: &mut Random) -> Vec<f64>
+    where
+        S: Scorer,
+        E: Fn(f64, usize) -> f64,
+    {
+        let run_key = self.run_key.clone();
+        let mut losses = Vec::with_capacity(samples);
+        let mut moves = Vec::new();
+        let mut current = 0.0;
+        for sample in 0..samples {
+            if sample % SAMPLES_PER_KEY == 0 {
+                self.run_key = self.cipher.random_key(rng);
+                self.cipher.constrain(&mut self.run_key);
+                current = energy(self.decode(scorer), self.output_buf.len());
+                moves = self.cipher.moves(&self.run_key);
+            }
+            if moves.is_empty() {
+                break;
+            }
+            let step = moves[rng.gen_range(0..moves.len())];
+            let (undo, score) = self.step(scorer, step);
+            let loss = current - energy(score, self.output_buf.len());
+            self.undo(scorer, undo);
+            if loss > 0.0 {
+                losses.push(loss);
+            }
+        }
+        self.run_key = run_key;
+        self.decode(scorer);
+        losses
+    }
+
+    /// A schedule of the given cooling whose start and end temperatures make
+    /// the search accept the shares `start_rate` and `end_rate` of worsening
+    /// moves from random keys, see `Schedule::calibrated`. `None` when no
+    /// move worsens or the search ignores the temperature.
+    pub fn calibrate<S, E, SEARCH>(
+        &mut self,
+        scorer: &S,
+        energy: &E,
+        search: &SEARCH,
+        cooling: Cooling,
+        (start_rate, end_rate): (f64, f64),
+        rng: &mut Random,
+    ) -> Option<Schedule>
+    where
+        S: Scorer,
+        E: Fn(f64, usize) -> f64,
+        SEARCH: Search,
+    {
+        let losses = self.sample_losses(scorer, energy, CALIBRATION_SAMPLES, rng);
+        Schedule::calibrated(cooling, search, &losses, start_rate, end_rate)
+    }
+
+    /// Keeps the running key as the best one and applies the crib callback.
+    fn keep_top<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>(
+        &mut self,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+    ) where
+        SCORER: Scorer,
+        CRIB: Fn(&mut Climber<C>),
+    {
+        self.top_key.clone_from(&self.run_key);
+        self.top_transposition = self.run_transposition;
+        self.top_energy = self.run_energy;
+        if let Some(shared) = &self.shared {
+            shared.offer(self.top_energy, self.top_transposition, &self.top_key);
+        }
+        (config.crib)(self);
+        self.decode(&config.scorer);
+    }
+
+    /// Proposes another candidate transposition for the running key.
+    fn switch_transposition<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>(
+        &mut self,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        search: &mut SEARCH,
+        rng: &mut Random,
+    ) where
+        SCORER: Scorer,
+        ENERGY: Fn(f64, usize) -> f64,
+        SEARCH: Search,
+        CRIB: Fn(&mut Climber<C>),
+    {
+        let previous = self.run_transposition;
+        self.transpose(rng.gen_range(0..self.transpositions.len()));
+        let energy = (config.energy)(self.decode(&config.scorer), self.output_buf.len());
+        if !search.accept(self.run_energy, energy, self.temperature, rng) {
+            self.transpose(previous);
+            self.decode(&config.scorer);
+            return;
+        }
+        self.run_energy = energy;
+        if self.run_energy > self.top_energy {
+            self.keep_top(config);
+        }
+    }
 }
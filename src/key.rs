@@ -1,4 +1,6 @@
 use crate::encoding::{Alphabet, Char};
+use crate::error::{Error, Result};
+use rand::Rng;
 use std::ops::{Index, IndexMut};
 
 #[derive(Clone, Debug)]
@@ -9,18 +11,17 @@ pub struct Crib {
 
 impl Crib {
     pub fn new(len: usize) -> Self {
-        let mut loose = vec![0; len];
+        let loose = (0..len).collect();
         let fixed = vec![];
-        for index in 0..len {
-            loose[index] = index;
-        }
         Self { loose, fixed }
     }
 
-    pub fn sample(&self) -> usize {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        self.loose[rng.gen_range(0..self.loose.len())]
+    /// A random loose item, none when the crib fixes every item.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<usize> {
+        if self.loose.is_empty() {
+            return None;
+        }
+        Some(self.loose[rng.gen_range(0..self.loose.len())])
     }
 
     pub fn find_loose_index(&self, item: usize) -> Option<usize> {
@@ -54,6 +55,11 @@ impl Key {
         self.0.len()
     }
 
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     #[inline(always)]
     pub fn swap(&mut self, i: usize, j: usize) {
         self.0.swap(i, j);
@@ -71,15 +77,80 @@ impl Key {
         self.0.copy_from_slice(key.0.as_slice());
     }
 
-    pub fn splice(&mut self, key: &Key) {
+    /// Lists the cipher characters decoding to each output character.
+    pub fn inverse(&self, output_alphabet: &Alphabet) -> Vec<Vec<Char>> {
+        let mut inverse = vec![Vec::new(); output_alphabet.len()];
+        for (cipher_char, &output_char) in self.0.iter().enumerate() {
+            inverse[output_char as usize].push(cipher_char as Char);
+        }
+        inverse
+    }
+
+    /// Encodes by picking a random homophone for every output character.
+    pub fn encode<R: Rng + ?Sized>(
+        &self,
+        output: &[Char],
+        cipher: &mut [Char],
+        output_alphabet: &Alphabet,
+        rng: &mut R,
+    ) -> Result<()> {
+        use rand::seq::SliceRandom;
+        let inverse = self.inverse(output_alphabet);
+        for (index, &c) in output.iter().enumerate() {
+            cipher[index] = *inverse[c as usize]
+                .choose(rng)
+                .ok_or_else(|| Error::Invalid(format!("No cipher symbol decrypts to output character {}", c)))?;
+        }
+        Ok(())
+    }
+
+    pub fn splice<R: Rng + ?Sized>(&mut self, key: &Key, rng: &mut R) {
         for index in 0..key.len() {
-            if crate::util::probability(0.5) {
+            if crate::util::probability(0.5, rng) {
                 self[index] = key[index];
             }
         }
     }
 
-    pub fn random_distribution(len: usize, frq: &[f64]) -> Self {
+    /// Keeps a random slice of the key and reorders the other entries as
+    /// they appear in `key`, keeping the count of every output character.
+    pub fn order_crossover<R: Rng + ?Sized>(&mut self, key: &Key, rng: &mut R) {
+        let (a, b) = (rng.gen_range(0..=self.len()), rng.gen_range(0..=self.len()));
+        let keep = (0..self.len()).map(|index| index >= a.min(b) && index < a.max(b)).collect::<Vec<_>>();
+        self.fill_in_order(&keep, key);
+    }
+
+    /// Keeps each entry of the key with probability 0.5 and reorders the
+    /// other entries as they appear in `key`, keeping the count of every
+    /// output character.
+    pub fn position_crossover<R: Rng + ?Sized>(&mut self, key: &Key, rng: &mut R) {
+        let keep = (0..self.len()).map(|_| crate::util::probability(0.5, rng)).collect::<Vec<_>>();
+        self.fill_in_order(&keep, key);
+    }
+
+    /// Rewrites the entries not kept with the same characters, in their
+    /// order in `key`. Characters `key` has fewer of keep their order.
+    fn fill_in_order(&mut self, keep: &[bool], key: &Key) {
+        let free = (0..self.len()).filter(|&index| !keep[index]).collect::<Vec<_>>();
+        let original = free.iter().map(|&index| self[index]).collect::<Vec<_>>();
+        let size = self.0.iter().chain(key.0.iter()).map(|&c| c as usize + 1).max().unwrap_or(0);
+        let mut counts = vec![0usize; size];
+        for &c in original.iter() {
+            counts[c as usize] += 1;
+        }
+        let mut ordered = Vec::with_capacity(free.len());
+        for &c in key.0.iter().chain(original.iter()) {
+            if counts[c as usize] > 0 {
+                counts[c as usize] -= 1;
+                ordered.push(c);
+            }
+        }
+        for (&index, c) in free.iter().zip(ordered) {
+            self[index] = c;
+        }
+    }
+
+    pub fn random_distribution<R: Rng + ?Sized>(len: usize, frq: &[f64], rng: &mut R) -> Self {
         let mut tmp = Self::new(len);
         for index in 0..tmp.len() {
             tmp[index] = 0;
@@ -110,7 +181,7 @@ impl Key {
             }
 
             // Probabilistically set character
-            if crate::util::probability(frq[frq_index]) {
+            if crate::util::probability(frq[frq_index], rng) {
                 key[key_index] = frq_index as Char;
                 tmp[key_index] = 1;
             }
@@ -121,39 +192,51 @@ impl Key {
         key
     }
 
-    pub fn random(cipher_alphabet: &Alphabet, output_alphabet: &Alphabet) -> Self {
+    pub fn random<R: Rng + ?Sized>(
+        cipher_alphabet: &Alphabet,
+        output_alphabet: &Alphabet,
+        rng: &mut R,
+    ) -> Self {
         let mut init = Self::new(cipher_alphabet.len());
-        init.randomize(cipher_alphabet, output_alphabet);
+        init.randomize(cipher_alphabet, output_alphabet, rng);
         init
     }
 
-    pub fn randomize(&mut self, cipher_alphabet: &Alphabet, output_alphabet: &Alphabet) {
-        let mut cipher = cipher_alphabet.char_set();
-        let mut output = output_alphabet.char_set();
-
-        while cipher.len() > 0 && output.len() > 0 {
-            use crate::util;
-            let cipher_char = util::take_random_element_from_set(&mut cipher);
-            let output_char = util::take_random_element_from_set(&mut output);
-            self[cipher_char as usize] = output_char;
+    pub fn randomize<R: Rng + ?Sized>(
+        &mut self,
+        cipher_alphabet: &Alphabet,
+        output_alphabet: &Alphabet,
+        rng: &mut R,
+    ) {
+        use rand::seq::SliceRandom;
+        let mut cipher = cipher_alphabet.char_vec();
+        let mut output = output_alphabet.char_vec();
+        cipher.shuffle(rng);
+        output.shuffle(rng);
+
+        let paired = cipher.len().min(output.len());
+        for index in 0..paired {
+            self[cipher[index] as usize] = output[index];
         }
 
-        for &cipher_char in cipher.iter() {
-            let output_char = output_alphabet.random_char();
+        for &cipher_char in cipher[paired..].iter() {
+            let output_char = output_alphabet.random_char(rng);
             self[cipher_char as usize] = output_char;
         }
     }
 
-    pub fn random_putc(&mut self, alphabet: &Alphabet) {
+    pub fn random_putc<R: Rng + ?Sized>(&mut self, alphabet: &Alphabet, rng: &mut R) {
         let len = self.0.len();
-        self.0[len-3] = alphabet.random_char();
-        self.0[len-2] = alphabet.random_char();
-        self.0[len-1] = alphabet.random_char();
+        self.0[len-3] = alphabet.random_char(rng);
+        self.0[len-2] = alphabet.random_char(rng);
+        self.0[len-1] = alphabet.random_char(rng);
     }
 
-    pub fn random_swap(&mut self, crib: &Crib) {
-        let (a, b) = (crib.sample(), crib.sample());
-        self.0.swap(a, b);
+    /// Swaps two loose symbols, keeps the key when the crib fixes them all.
+    pub fn random_swap<R: Rng + ?Sized>(&mut self, crib: &Crib, rng: &mut R) {
+        if let (Some(a), Some(b)) = (crib.sample(rng), crib.sample(rng)) {
+            self.0.swap(a, b);
+        }
     }
 }
 
@@ -191,6 +274,11 @@ impl Keys {
         self[0].len()
     }
 
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty() || self[0].is_empty()
+    }
+
     #[inline(always)]
     pub fn decode(&self, cipher: &[Char], output: &mut [Char]) {
         for (i, &c) in cipher.iter().enumerate() {
@@ -206,30 +294,36 @@ impl Keys {
         }
     }
 
-    pub fn random(
+    pub fn random<R: Rng + ?Sized>(
         count: usize,
         cipher_alphabet: &Alphabet,
         output_alphabet: &Alphabet,
+        rng: &mut R,
     ) -> Self {
         let mut init = Self::new(count, cipher_alphabet.len());
-        init.randomize(cipher_alphabet, output_alphabet);
+        init.randomize(cipher_alphabet, output_alphabet, rng);
         init
     }
 
-    pub fn randomize(&mut self, cipher_alphabet: &Alphabet, output_alphabet: &Alphabet) {
+    pub fn randomize<R: Rng + ?Sized>(
+        &mut self,
+        cipher_alphabet: &Alphabet,
+        output_alphabet: &Alphabet,
+        rng: &mut R,
+    ) {
         for key in 0..self.count() {
-            self[key].randomize(cipher_alphabet, output_alphabet);
+            self[key].randomize(cipher_alphabet, output_alphabet, rng);
         }
     }
 
     #[inline(always)]
-    pub fn random_putc(&mut self, key: usize, alphabet: &Alphabet) {
-        self[key].random_putc(alphabet);
+    pub fn random_putc<R: Rng + ?Sized>(&mut self, key: usize, alphabet: &Alphabet, rng: &mut R) {
+        self[key].random_putc(alphabet, rng);
     }
 
     #[inline(always)]
-    pub fn random_swap(&mut self, key: usize, crib: &Crib) {
-        self[key].random_swap(crib);
+    pub fn random_swap<R: Rng + ?Sized>(&mut self, key: usize, crib: &Crib, rng: &mut R) {
+        self[key].random_swap(crib, rng);
     }
 }
 
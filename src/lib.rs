@@ -0,0 +1,74 @@
+//! Solvers for classic ciphers: homophonic substitution, periodic
+//! polyalphabetic ciphers and transpositions, scored by n-gram models.
+//!
+//! Texts are encoded into `Char` indices of an `Encoding` before solving.
+//! `Frequency` and `Interpolated` score them as `Scorer`s. A `Cipher`
+//! describes the keys of a family and the moves between them, `Climber`
+//! climbs the keys of any `Cipher` under a `Search` strategy, at the
+//! temperatures of a `Schedule`, and `Parallel` runs its restarts on threads.
+//! `Tempering` exchanges chains climbing at a ladder of temperatures and
+//! `Genetic` evolves a population of substitution keys. `analyze`, `period`
+//! and `repeats` hold ciphertext statistics. `mode::Solver` and
+//! `mode::Encryption` solve and encrypt whole texts of each cipher family.
+
+pub mod analyze;
+pub mod ciphers;
+pub mod pre;
+pub mod util;
+
+pub mod delta;
+pub mod encoding;
+pub mod error;
+pub mod genetic;
+pub mod hill;
+pub mod key;
+pub mod mode;
+pub mod model;
+pub mod parallel;
+pub mod period;
+pub mod periodic;
+pub mod repeats;
+pub mod route;
+pub mod schedule;
+pub mod search;
+pub mod stats;
+pub mod tempering;
+pub mod transposition;
+
+use std::collections::HashMap;
+
+pub use ciphers::Cipher;
+pub use encoding::{Alphabet, Char, Encoding, Frequency, Interpolated, Scorer};
+pub use error::{Error, Result};
+pub use hill::Climber;
+pub use key::{Key, Keys};
+pub use pre::Pre;
+pub use schedule::{Cooling, Schedule};
+pub use search::{Search, Strategy};
+
+/// Reads a file keeping only the characters of the encoding, after
+/// normalizing them, and encodes it.
+pub fn read_encoded_text_from_file(
+    path: &str,
+    encoding: &Encoding,
+    normalize: HashMap<char, char>,
+) -> Result<Vec<Char>> {
+    let pre = Pre::from_emitter_and_normalizer(encoding.char_set(), normalize);
+    let text = pre.process(&util::read_file_to_string(path)?);
+    if text.is_empty() {
+        return Err(Error::Invalid(format!("{}: no symbols of the alphabet", path)));
+    }
+    encoding.encode_str(&text)
+}
+
+pub const LATIN: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const ZODIAC: &str = "!#%()+/=@\\^_56789ABcdDeEfFGHIjJkKlLMNOpPqQrRStTUVWXYzZ";
+pub const SYMBOL: &str = "!@6/_^&*()%+=-|987#5$,.0[]?12";
+
+pub const ENERGY_VALUE: f64 = 5000.0;
+pub const EXPECT_VALUE: f64 = 5.0;
+
+/// Maps the score of an output of `len` characters to the climbed energy.
+pub fn energy(score: f64, len: usize) -> f64 {
+    (score * ENERGY_VALUE) / len as f64
+}
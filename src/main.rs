@@ -1,129 +1,200 @@
-pub mod pre;
-pub mod util;
-
-pub mod encoding;
-pub mod hill;
-pub mod key;
-pub mod stats;
+mod cli;
 
+use cipher::encoding::{self, Char, Encoding, Frequency, Scorer};
+use cipher::error::{self, Error};
+use cipher::mode::{self, Mode};
+use cipher::{analyze, ciphers, genetic, hill, key, model, period, periodic, pre, repeats, stats, transposition, util};
+use cipher::{energy, read_encoded_text_from_file};
 use std::collections::HashMap;
-pub fn print_map<K: std::fmt::Display, V: std::fmt::Display>(map: &HashMap<K, V>) {
-    for (key, val) in map.iter() {
-        println!("{}: {}", key, val);
-    }
-}
 
-use encoding::{Char, Encoding};
-pub fn read_encoded_text_from_file(
-    path: &str,
-    encoding: &Encoding,
-    normalize: HashMap<char, char>,
-) -> Vec<Char> {
-    use pre::Pre;
-    let pre = Pre::from_emitter_and_normalizer(encoding.char_set(), normalize);
-    encoding.encode_str(&pre.process(&util::read_file_to_string(path)))
-}
+fn main() {
+    use cli::Command;
 
-pub fn print_if(print: bool, message: &str) -> bool {
-    if print {
-        println!("{}", message);
+    let command = match Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    let result = match command {
+        Command::Solve(options) => solve(&options),
+        Command::Analyze(options) => analyze(&options),
+        Command::Encrypt(options) => encrypt(&options),
+        Command::BuildNgrams(options) => build_ngrams(&options),
+        Command::ConvertNgrams(options) => convert_ngrams(&options),
+        Command::Help => {
+            print!("{}", cli::USAGE);
+            Ok(())
+        }
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        std::process::exit(1);
     }
-    print
 }
 
-pub fn lavy_accept(prev: f64, next: f64) -> bool {
-    if next > prev {
-        return true;
-    }
-    let degradation = next - prev;
-    let p = (-degradation / TEMPERATURE).exp() - 1.0;
-    p > THRESHOLD && util::probability(p)
+fn random_seed(seed: Option<u64>) -> u64 {
+    let seed = seed.unwrap_or_else(rand::random);
+    println!("Seed: {}", seed);
+    seed
 }
 
-pub const LATIN: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-pub const ZODIAC: &'static str = "!#%()+/=@\\^_56789ABcdDeEfFGHIjJkKlLMNOpPqQrRStTUVWXYzZ";
-pub const SYMBOL: &'static str = "!@6/_^&*()%+=-|987#5$,.0[]?12";
-
-pub const TEMPERATURE: f64 = 3500.0;
-pub const THRESHOLD: f64 = 0.0085;
-
-pub const ENERGY_VALUE: f64 = 5000.0;
-pub const EXPECT_VALUE: f64 = 5.0;
-
-fn main() {
-    use encoding::Frequency;
-    use hill::{Climber, Config};
-    use key::Key;
+fn seeded_random(seed: Option<u64>) -> util::Random {
+    util::seeded_random(random_seed(seed))
+}
 
+fn latin_normalizer() -> HashMap<char, char> {
     let mut pre = pre::Pre::new();
     pre::latin(&mut pre);
+    pre.normalize
+}
 
-    let homophone_ratio = 0.2f64;
+fn solve(options: &cli::Solve) -> error::Result<()> {
+    use encoding::Interpolated;
 
     println!("Creating alphabet and encoding");
-    let output_encoding = Encoding::from_alphabet_string(LATIN);
-    let cipher_encoding = Encoding::from_alphabet_string(ZODIAC);
+    let output_encoding = Encoding::from_alphabet_string(&options.output_alphabet);
+    let cipher_encoding = Encoding::from_alphabet_string(&options.cipher_alphabet);
 
     println!("Loading ciphertext");
     let cipher_buf =
-        read_encoded_text_from_file("./data/ciphers/z408.txt", &cipher_encoding, HashMap::new());
+        read_encoded_text_from_file(&options.ciphertext, &cipher_encoding, HashMap::new())?;
 
     println!("Loading frequency data");
-    let frequency5 = Frequency::new(
-        &stats::Frequency::from_count(&stats::Count::from_file("./data/en_5gram.txt", 5)),
-        &output_encoding,
-    );
-    // let frequency4 = Frequency::new(
-    //     &stats::Frequency::from_count(&stats::Count::from_file("./data/en_4gram.txt", 4)),
-    //     &output_encoding,
-    // );
-    let frequency1 = Frequency::new(
-        &stats::Frequency::from_count(&stats::Count::from_file("./data/en_1gram.txt", 1)),
-        &output_encoding,
-    );
+    let load = |path: &str, order: usize| {
+        use model::Model;
+        if Model::is_model_file(path) {
+            let model = Model::from_file(path).map_err(|e| Error::io(path, e))?;
+            if model.alphabet != options.output_alphabet {
+                return Err(Error::Invalid(format!("Model {} does not use the output alphabet", path)));
+            }
+            return Ok(model.frequency);
+        }
+        Frequency::new(
+            &stats::Frequency::smoothed(
+                &stats::Count::from_file_with_encoding(path, order, &output_encoding)?,
+                options.smoothing,
+                output_encoding.base_alphabet().len(),
+            ),
+            &output_encoding,
+        )
+    };
+    let scorer: Box<dyn Scorer + Sync> = if options.interpolate.is_empty() {
+        Box::new(load(&options.ngrams, options.order)?)
+    } else {
+        let models = options
+            .interpolate
+            .iter()
+            .map(|(order, weight, path)| Ok((load(path, *order)?, *weight)))
+            .collect::<error::Result<_>>()?;
+        Box::new(Interpolated::new(models, options.combine)?)
+    };
+    let solver = mode::Solver {
+        scorer: &*scorer,
+        cipher_encoding: cipher_encoding.clone(),
+        output_encoding: output_encoding.clone(),
+        search: options.search.clone(),
+        schedule: options.schedule(),
+        calibrate: options.calibrate,
+        cycle: options.cycle,
+        restarts: options.restarts,
+        leaderboard: options.leaderboard,
+        seed: random_seed(options.seed),
+    };
+    match options.mode {
+        Mode::Substitution => solve_substitution(options, &solver, cipher_buf),
+        Mode::Periodic(variant) => solve_periodic(options, &solver, variant, cipher_buf),
+        Mode::Transposition(kind) => {
+            println!("Climbing...");
+            let solutions = solver.columnar(cipher_buf, kind, &options.widths, |solution| {
+                let widths = solution.key.iter().map(Vec::len).collect::<Vec<_>>();
+                println!("Width: {:?} | Score: {:>8.3}", widths, solution.energy);
+            })?;
+            for (rank, solution) in solutions.iter().enumerate() {
+                println!("---");
+                println!("Rank: {} | Score: {:.3}", rank + 1, solution.energy);
+                println!("Key: {:?}", solution.key);
+                println!("{}", output_encoding.decode_str(&solution.output));
+            }
+            println!("---");
+            Ok(())
+        }
+        Mode::Route => {
+            println!("Scoring routes...");
+            for (rank, (energy, route, transposition)) in solver.routes(&cipher_buf, &options.widths)?.iter().enumerate() {
+                println!("---");
+                println!("Rank: {} | Score: {:.3} | Route: {}", rank + 1, energy, route);
+                println!("{}", output_encoding.decode_str(&transposition.apply(&cipher_buf)));
+            }
+            println!("---");
+            Ok(())
+        }
+    }
+}
+
+fn solve_substitution(options: &cli::Solve, solver: &mode::Solver<dyn Scorer + Sync>, cipher_buf: Vec<Char>) -> error::Result<()> {
+    use ciphers::{Cipher, Substitution};
+    use hill::{Climber, Config};
+    use key::Key;
+    use mode::{Method, Progress};
+
+    let output_encoding = &solver.output_encoding;
+    let letters = Frequency::new(
+        &stats::Frequency::from_count(&stats::Count::from_file_with_encoding(&options.letters, 1, output_encoding)?),
+        output_encoding,
+    )?;
     println!("Loading dictionary data");
     // let dict = stats::Dictionary::from_file("./data/en_dict10.txt", &pre);
 
-    let mut letter_distribution = vec![0.0; 26];
-    for (key, val) in frequency1.map.iter() {
-        letter_distribution[key[0] as usize] = *val;
-    }
+    let letter_distribution = output_encoding
+        .base_alphabet()
+        .char_vec()
+        .iter()
+        .map(|&c| letters.get(&[c]))
+        .collect::<Vec<_>>();
 
-    use std::cell::RefCell;
-    let previous_progress = RefCell::new(0.0f64);
+    use std::sync::Mutex;
+    let previous_progress = Mutex::new(0.0f64);
+    let print_progress = options.tempering == 0 && (options.threads == 1 || options.restarts == 1);
 
-    let cycle = 1000;
-    let derive_cycle = 100000;
-    let mutate_cycle = 1000;
-    let config = Config {
+    let cycle = options.cycle;
+    let mut config = Config {
         cycle,
-        mutate_cycle,
-        derive_cycle,
-        report: |climber, count, accepted, rejected| {
+        mutate_cycle: options.mutate_cycle,
+        derive_cycle: options.derive_cycle,
+        report: |climber: &Climber<Substitution>, count, accepted, rejected| {
+            if !print_progress {
+                return;
+            }
+            let mut previous_progress = previous_progress.lock().unwrap();
+            if count == 0 {
+                *previous_progress = 0.0;
+            }
             let progress = ((count as f64 / cycle as f64) * 100.0).floor();
-            if progress > *previous_progress.borrow() {
-                *previous_progress.borrow_mut() = progress;
+            if progress > *previous_progress {
+                *previous_progress = progress;
                 let ratio = accepted as f64 / rejected as f64;
                 println!(
-                    "Progress: {:>3.0}% | Score: {:>8.3} / {:>8.3} | Accept/Reject: {:>6.3}",
-                    progress, climber.top_energy, climber.run_energy, ratio
+                    "Progress: {:>3.0}% | Score: {:>8.3} / {:>8.3} | Accept/Reject: {:>6.3} | Temperature: {:>8.3}",
+                    progress, climber.top_energy, climber.run_energy, ratio, climber.temperature
                 );
             }
         },
-        random_key: |climber| {
-            println!("Initializing key");
+        random_key: |climber: &mut Climber<Substitution>, rng: &mut util::Random| {
             climber.run_key.copy(&Key::random_distribution(
-                climber.cipher_alphabet.len(),
+                climber.cipher.cipher_alphabet.len(),
                 &letter_distribution,
+                rng,
             ));
         },
-        derive_key: |_climber| {
+        derive_key: |_climber: &mut Climber<Substitution>, _rng: &mut util::Random| {
             // key.random_swap(&state.crib);
         },
-        mutate_key: |climber| {
-            climber.run_key.random_putc(&climber.output_alphabet);
+        mutate_key: |climber: &mut Climber<Substitution>, rng: &mut util::Random| {
+            climber.cipher.mutate(&mut climber.run_key, rng);
         },
-        crib: |_climber| {
+        crib: |_climber: &mut Climber<Substitution>| {
             // key.random_swap(&state.crib);
             // let text = climber.output_encoding.decode_str(&climber.output_buf);
             // let words = stats::find_words(&text, &dict.0);
@@ -136,26 +207,371 @@ fn main() {
             //     }
             // }
         },
-        energy: |output| {
-            let score = frequency5.score(output);
-            (score * ENERGY_VALUE) / output.len() as f64
-        },
-        accept: |prev, next| lavy_accept(prev, next),
+        scorer: solver.scorer,
+        energy,
+        search: solver.search.clone(),
+        schedule: solver.schedule,
     };
 
+    let transpositions = options
+        .transpositions
+        .iter()
+        .map(|spec| transposition::Transposition::parse(spec, cipher_buf.len()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::Invalid)?;
+
     println!("Climbing...");
     let mut climber = Climber::new(
         cipher_buf,
-        cipher_encoding.clone(),
+        solver.cipher_encoding.clone(),
         output_encoding.clone(),
-        homophone_ratio,
+        options.homophone_ratio,
+    )?;
+    println!("Key length: {}", climber.cipher.cipher_alphabet.len());
+    config.schedule = solver.schedule(&mut climber, &mut util::seeded_random(solver.seed));
+    if options.calibrate.is_some() {
+        println!("Temperature: {:.3} to {:.3}", config.schedule.start, config.schedule.end);
+    }
+    let method = if options.tempering > 0 {
+        Method::Tempering {
+            chains: options.tempering,
+            exchange_cycle: options.exchange_cycle,
+        }
+    } else if options.genetic > 0 {
+        Method::Genetic(genetic::Genetic {
+            population: options.genetic,
+            tournament: options.tournament,
+            elites: options.elites,
+            crossover: options.crossover,
+            mutation_rate: options.mutation,
+            ..genetic::Genetic::default()
+        })
+    } else {
+        Method::Restarts {
+            threads: options.threads,
+            share: options.share,
+        }
+    };
+    let step = (options.cycle / 20).max(1);
+    let leaderboard = solver.substitution(
+        &climber,
+        config,
+        transpositions,
+        options.anneal_transpositions,
+        &options.cribs,
+        &method,
+        |progress| match progress {
+            Progress::Restart(solution, leaderboard) => {
+                let best = leaderboard.best().map(|s| s.energy).unwrap_or(solution.energy);
+                println!(
+                    "Restart: {:>4} | Score: {:>8.3} | Best: {:>8.3}",
+                    solution.restart, solution.energy, best
+                );
+            }
+            Progress::Round(round, chains) => {
+                let best = chains.iter().map(|c| c.top_energy).fold(hill::MIN_ENERGY, f64::max);
+                println!(
+                    "Round: {:>4} | Cold: {:>8.3} | Best: {:>8.3}",
+                    round, chains[0].run_energy, best
+                );
+            }
+            Progress::Rungs(rungs) => {
+                for (index, rung) in rungs.iter().enumerate() {
+                    println!(
+                        "Rung: {:>3} | Temperature: {:>10.3} | Swaps: {:>5} / {:>5} | Acceptance: {:.3}",
+                        index,
+                        rung.temperature,
+                        rung.swaps,
+                        rung.attempts,
+                        rung.acceptance()
+                    );
+                }
+            }
+            Progress::Generation(generation) => {
+                if generation.index % step == 0 || generation.index + 1 == options.cycle {
+                    println!(
+                        "Generation: {:>5} | Best: {:>8.3} | Mean: {:>8.3} | Diversity: {:.3}",
+                        generation.index, generation.best, generation.mean, generation.diversity
+                    );
+                }
+            }
+        },
+    )?;
+
+    for (rank, solution) in leaderboard.solutions.iter().enumerate() {
+        println!("---");
+        println!(
+            "Rank: {} | Restart: {} | Score: {:.3}",
+            rank + 1,
+            solution.restart,
+            solution.energy
+        );
+        if let Some(spec) = options.transpositions.get(solution.transposition) {
+            println!("Transposition: {}", spec);
+        }
+        println!("Key: {}", climber.cipher.format_key(&solution.key));
+        println!("{}", output_encoding.decode_str(&solution.output));
+    }
+    println!("---");
+    Ok(())
+}
+
+fn solve_periodic(options: &cli::Solve, solver: &mode::Solver<dyn Scorer + Sync>, variant: periodic::Variant, cipher_buf: Vec<Char>) -> error::Result<()> {
+    let (cipher_encoding, output_encoding) = (&solver.cipher_encoding, &solver.output_encoding);
+    let period = match options.period {
+        Some(period) => period,
+        None => {
+            let letters = output_encoding.base_alphabet().len();
+            let candidates = period::estimate(&cipher_buf, letters, period::MAX_PERIOD);
+            for candidate in candidates.iter().take(3) {
+                println!(
+                    "Period candidate: {:>3} | Confidence: {:.3}",
+                    candidate.period, candidate.confidence
+                );
+            }
+            let period = candidates.first().map_or(1, |c| c.period);
+            println!("Estimated period: {}", period);
+            period
+        }
+    };
+
+    println!("Climbing...");
+    let solution = solver.periodic(cipher_buf, variant, period)?;
+
+    println!("---");
+    println!("Period: {} | Score: {:.3}", period, solution.energy);
+    if variant.is_shift() {
+        let word = (0..period).map(|column| variant.shift_of(&solution.key[column]) as Char).collect::<Vec<_>>();
+        println!("Key: {}", output_encoding.decode_str(&word));
+    } else {
+        let cipher_chars = cipher_encoding.base_alphabet().char_vec();
+        let mut column_buf = vec![0; cipher_chars.len()];
+        println!("Cipher: {}", cipher_encoding.decode_str(&cipher_chars));
+        for column in 0..period {
+            solution.key[column].decode(&cipher_chars, &mut column_buf);
+            println!("Key {:>3}: {}", column, output_encoding.decode_str(&column_buf));
+        }
+    }
+    println!("{}", output_encoding.decode_str(&solution.output));
+    println!("---");
+    Ok(())
+}
+
+fn analyze(options: &cli::Analyze) -> error::Result<()> {
+    let cipher_encoding = Encoding::from_alphabet_string(&options.cipher_alphabet);
+    let cipher_alphabet = cipher_encoding.base_alphabet();
+    let cipher_buf =
+        read_encoded_text_from_file(&options.ciphertext, &cipher_encoding, HashMap::new())?;
+
+    let mut counts = vec![0usize; cipher_alphabet.len()];
+    for &c in cipher_buf.iter() {
+        counts[c as usize] += 1;
+    }
+    let mut ordered = counts.iter().enumerate().filter(|(_, &n)| n > 0).collect::<Vec<_>>();
+    ordered.sort_by(|a, b| b.1.cmp(a.1));
+
+    println!("Length: {}", cipher_buf.len());
+    println!("Symbols: {} / {}", ordered.len(), cipher_alphabet.len());
+    println!(
+        "Index of coincidence: {:.3}",
+        util::index_of_coincidence(&cipher_buf, &cipher_alphabet)
+    );
+
+    let reference = english_reference(options, &cipher_encoding)?;
+    let features = analyze::Features::new(&cipher_buf, &reference);
+    let optional = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.3}", v));
+    println!("---");
+    println!("Raw IoC: {:.4}", features.ioc);
+    println!(
+        "Max periodic IoC: {:.4} at period {}",
+        features.max_periodic_ioc, features.max_period
     );
-    climber.crib_str(0, "ILIKEKILLINGPEOPLE");
-    climber.climb(&config);
-    climber.top_key.decode(&climber.cipher_buf, &mut climber.output_buf);
+    println!("Max kappa: {:.4} at shift {}", features.max_kappa, features.max_kappa_shift);
+    println!("Digraphic IoC: {:.5} | Even digraphic IoC: {:.5}", features.dic, features.edi);
+    println!(
+        "Long repeats: {:.3} | Odd spaced repeats: {:.3}",
+        features.long_repeat, features.odd_repeats
+    );
+    println!("Log digraphic index: {}", optional(features.ldi));
+    println!(
+        "Doubled: {} | Doubled at even offsets: {} | Even length: {}",
+        features.doubled, features.even_doubled, features.even_length
+    );
+    println!(
+        "Chi-square: {} | Sorted chi-square: {:.3}",
+        optional(features.chi_square),
+        features.sorted_chi_square
+    );
+    println!("Entropy: {:.3} bits", features.entropy);
+    println!("---");
+    for (family, score) in analyze::rank(&features) {
+        println!("{:<14} {:.3}", family, score);
+    }
 
-    println!("Key: {:?}", climber.top_key);
+    let distances = period::kasiski_distances(&cipher_buf);
+    let letters = reference.letters.len();
+    println!("---");
+    println!("Friedman period: {:.2}", period::friedman(&cipher_buf, letters));
+    println!("Kasiski repeats: {} | GCD: {}", distances.len(), period::gcd(&distances));
+    println!("Period | Confidence | Column IoC | Kasiski");
+    for candidate in period::estimate(&cipher_buf, letters, period::MAX_PERIOD).iter().take(5) {
+        println!(
+            "{:>6} | {:>10.3} | {:>10.4} | {:>7.2}",
+            candidate.period, candidate.confidence, candidate.ioc, candidate.kasiski
+        );
+    }
     println!("---");
-    println!("{}", output_encoding.decode_str(&climber.output_buf));
+    for (c, count) in ordered {
+        println!("{} {}", cipher_encoding.decode_str(&[c as Char]), count);
+    }
+
+    let max_period = options.max_period.min(cipher_buf.len().saturating_sub(1));
+    if max_period == 0 {
+        return Ok(());
+    }
+    let mut rng = seeded_random(options.seed);
+    let baseline = repeats::Baseline::shuffled(&cipher_buf, max_period, options.shuffles, &mut rng);
     println!("---");
+    println!("Bigram repeats at period n, against {} shuffles", options.shuffles);
+    println!("Period | Repeats | Baseline |      Z");
+    for (index, &count) in repeats::repeats_table(&cipher_buf, max_period).iter().enumerate() {
+        let period = index + 1;
+        println!(
+            "{:>6} | {:>7} | {:>8.2} | {:>6.2}",
+            period,
+            count,
+            baseline.mean[index],
+            baseline.z_score(period, count)
+        );
+    }
+
+    if let Some(width) = options.width {
+        let mut readouts = repeats::diagonal_readouts(&cipher_buf, width, max_period, options.shuffles, &mut rng);
+        readouts.sort_by(|a, b| b.peak().1.total_cmp(&a.peak().1));
+        println!("---");
+        println!("Bigram repeats of diagonal readouts, against their own shuffles");
+        println!("Repeats |      Z | Peak period | Repeats |      Z | Route");
+        for readout in readouts {
+            let (period, z) = readout.peak();
+            println!(
+                "{:>7} | {:>6.2} | {:>11} | {:>7} | {:>6.2} | {}",
+                readout.repeats[0],
+                readout.z_score(1),
+                period,
+                readout.repeats[period - 1],
+                z,
+                readout.route
+            );
+        }
+    }
+    Ok(())
+}
+
+/// English statistics for `analyze`, aligned with the cipher symbols when
+/// every symbol is an English letter.
+fn english_reference(options: &cli::Analyze, cipher_encoding: &Encoding) -> error::Result<analyze::Reference> {
+    let letters = stats::Count::from_file(&options.letters, 1)?;
+    let total = letters.total() as f64;
+    let mut sorted = letters.map.values().map(|&n| n as f64 / total).collect::<Vec<_>>();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+
+    let symbols = cipher_encoding
+        .base_alphabet()
+        .char_vec()
+        .iter()
+        .map(|&c| cipher_encoding.decode_str(&[c]))
+        .collect::<Vec<_>>();
+    if !symbols.iter().all(|s| letters.map.contains_key(s)) {
+        return Ok(analyze::Reference {
+            letters: sorted,
+            aligned: None,
+            bigrams: None,
+        });
+    }
+    let aligned = symbols.iter().map(|s| letters.map[s] as f64 / total).collect();
+    let bigrams = stats::Count::from_file(&options.bigrams, 2)?;
+    let total = bigrams.total() as f64;
+    let mut table = Vec::with_capacity(symbols.len() * symbols.len());
+    for a in symbols.iter() {
+        for b in symbols.iter() {
+            let count = bigrams.map.get(&format!("{}{}", a, b)).copied().unwrap_or(0);
+            table.push(((count as f64 + 0.5) / total).log10());
+        }
+    }
+    Ok(analyze::Reference {
+        letters: sorted,
+        aligned: Some(aligned),
+        bigrams: Some(table),
+    })
+}
+
+fn encrypt(options: &cli::Encrypt) -> error::Result<()> {
+    let output_encoding = Encoding::from_alphabet_string(&options.output_alphabet);
+    let cipher_encoding = Encoding::from_alphabet_string(&options.cipher_alphabet);
+    let plain_buf =
+        read_encoded_text_from_file(&options.plaintext, &output_encoding, latin_normalizer())?;
+    let encryption = mode::Encryption {
+        mode: options.mode,
+        key: options.key.clone(),
+        period: options.period,
+        transposition: options.transposition.clone(),
+    };
+    let encrypted = encryption.encrypt(&plain_buf, &cipher_encoding, &output_encoding, &mut seeded_random(options.seed))?;
+    for (label, value) in encrypted.key.iter() {
+        eprintln!("{}: {}", label, value);
+    }
+
+    let ciphertext = cipher_encoding.decode_str(&encrypted.cipher_buf);
+    match &options.out {
+        Some(path) => std::fs::write(path, ciphertext).map_err(|e| Error::io(path, e))?,
+        None => println!("{}", ciphertext),
+    }
+    Ok(())
+}
+
+fn build_ngrams(options: &cli::BuildNgrams) -> error::Result<()> {
+    let pre = pre::Pre::from_emitter_and_normalizer(
+        Encoding::from_alphabet_string(&options.alphabet).char_set(),
+        latin_normalizer(),
+    );
+    for &order in options.orders.iter() {
+        let path = options.out_path(order);
+        if !options.overwrite && std::path::Path::new(&path).exists() {
+            eprintln!("Skipping {}, the file exists (see --overwrite)", path);
+            continue;
+        }
+        let mut count = stats::Count::from_corpus(&options.corpus, order, &pre)?;
+        let counted = count.map.len();
+        count.prune(options.min_count);
+        if let Some(top) = options.top {
+            count.truncate(top);
+        }
+        count.to_file(&path)?;
+        println!(
+            "Wrote {} of {} {}-grams to {}",
+            count.map.len(),
+            counted,
+            order,
+            path
+        );
+    }
+    Ok(())
+}
+
+fn convert_ngrams(options: &cli::ConvertNgrams) -> error::Result<()> {
+    use model::Model;
+    let model = Model::from_text_file(
+        &options.grams,
+        options.order,
+        &options.alphabet,
+        options.smoothing,
+    )?;
+    model.to_file(&options.out).map_err(|e| Error::io(&options.out, e))?;
+    println!(
+        "Wrote {} {}-gram model to {}",
+        if model.frequency.is_dense() { "dense" } else { "sparse" },
+        model.frequency.n,
+        options.out
+    );
+    Ok(())
 }
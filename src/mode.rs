@@ -0,0 +1,382 @@
+use crate::ciphers::{self, Cipher, Substitution};
+use crate::encoding::{Char, Encoding, Scorer};
+use crate::error::{self, Error, Result};
+use crate::genetic::{Generation, Genetic};
+use crate::hill::{Climber, Config};
+use crate::key::{Key, Keys};
+use crate::parallel::{Leaderboard, Parallel, Solution};
+use crate::periodic::Variant;
+use crate::route::Route;
+use crate::schedule::Schedule;
+use crate::search::Strategy;
+use crate::tempering::{Rung, Tempering};
+use crate::transposition::{self, Kind, Transposition};
+use crate::util::{self, Random};
+use std::str::FromStr;
+
+/// Cipher family to solve or encrypt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Substitution,
+    Periodic(Variant),
+    Transposition(Kind),
+    /// Every route transposition, see `route::Route::all`.
+    Route,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "substitution" => return Ok(Mode::Substitution),
+            "route" => return Ok(Mode::Route),
+            _ => {}
+        }
+        if let Ok(variant) = s.parse() {
+            return Ok(Mode::Periodic(variant));
+        }
+        if let Ok(kind) = s.parse() {
+            return Ok(Mode::Transposition(kind));
+        }
+        Err(format!("Unknown mode: {}", s))
+    }
+}
+
+/// How `Solver::substitution` searches the keys.
+#[derive(Debug, Clone)]
+pub enum Method {
+    /// Restarts on threads, see `Parallel`.
+    Restarts { threads: usize, share: f64 },
+    /// Parallel tempering of `chains` between the start and end temperatures
+    /// of the schedule, exchanged every `exchange_cycle` cycles.
+    Tempering { chains: usize, exchange_cycle: usize },
+    /// Evolves a population for `cycle` generations, whose seed and
+    /// leaderboard are those of the solver.
+    Genetic(Genetic),
+}
+
+/// Progress of `Solver::substitution`.
+pub enum Progress<'a> {
+    /// A restart finished, with the leaderboard of its run so far.
+    Restart(&'a Solution<Key>, &'a Leaderboard<Key>),
+    /// A round of tempering exchanged its chains, coldest first.
+    Round(usize, &'a [Climber<Substitution>]),
+    /// Tempering finished, with the exchanges of every rung.
+    Rungs(&'a [Rung]),
+    Generation(&'a Generation),
+}
+
+/// Solves whole ciphertexts of a mode, calibrating the schedule on random
+/// moves of the mode's cipher when asked to.
+pub struct Solver<'a, S: ?Sized> {
+    pub scorer: &'a S,
+    pub cipher_encoding: Encoding,
+    pub output_encoding: Encoding,
+    pub search: Strategy,
+    /// Used unless calibrated.
+    pub schedule: Schedule,
+    /// Shares of worsening moves accepted at the start and end temperatures,
+    /// see `Climber::calibrate`.
+    pub calibrate: Option<(f64, f64)>,
+    pub cycle: usize,
+    pub restarts: usize,
+    pub leaderboard: usize,
+    pub seed: u64,
+}
+
+impl<'a, S: Scorer + Sync + ?Sized> Solver<'a, S> {
+    /// The schedule, calibrated on the climber's cipher when asked to and
+    /// the search uses the temperature.
+    pub fn schedule<C: Cipher>(&self, climber: &mut Climber<C>, rng: &mut Random) -> Schedule {
+        self.calibrate
+            .and_then(|rates| climber.calibrate(&self.scorer, &crate::energy, &self.search, self.schedule.cooling, rates, rng))
+            .unwrap_or(self.schedule)
+    }
+
+    /// Climbs periodic keys of the given period from `restarts` random keys,
+    /// returning the best.
+    pub fn periodic(&self, cipher_buf: Vec<Char>, variant: Variant, period: usize) -> Result<Solution<Keys>> {
+        let cipher_alphabet = self.cipher_encoding.base_alphabet();
+        let cipher = ciphers::Polyalphabetic::new(variant, period, cipher_alphabet, self.output_encoding.clone())?;
+        let climber = Climber::with_cipher(cipher, cipher_buf, self.cipher_encoding.clone(), self.output_encoding.clone());
+        Ok(self.climb(climber))
+    }
+
+    /// Climbs `restarts` random keys of the climber's cipher one after
+    /// another, returning the best. Keys are neither derived nor mutated.
+    fn climb<C: Cipher + Sync>(&self, mut climber: Climber<C>) -> Solution<C::Key> {
+        let mut rng = util::seeded_random(self.seed);
+        let config = Config {
+            cycle: self.cycle,
+            derive_cycle: usize::MAX,
+            mutate_cycle: usize::MAX,
+            scorer: self.scorer,
+            energy: crate::energy,
+            search: self.search.clone(),
+            schedule: self.schedule(&mut climber, &mut rng),
+            random_key: |climber: &mut Climber<C>, rng: &mut Random| climber.run_key = climber.cipher.random_key(rng),
+            derive_key: |_: &mut Climber<C>, _: &mut Random| {},
+            mutate_key: |_: &mut Climber<C>, _: &mut Random| {},
+            report: |_: &Climber<C>, _: usize, _: usize, _: usize| {},
+            crib: |_: &mut Climber<C>| {},
+        };
+        let parallel = Parallel {
+            threads: 1,
+            restarts: self.restarts,
+            seed: self.seed,
+            leaderboard: 1,
+            share: 0.0,
+        };
+        parallel.run(&climber, &config, |_, _| {}).solutions.remove(0)
+    }
+
+    /// Climbs columnar keys of every width, or every pair of widths for
+    /// double columnar keys, calling `width` with the best keys of each.
+    /// Returns the best `leaderboard` of them.
+    pub fn columnar<W>(&self, cipher_buf: Vec<Char>, kind: Kind, widths: &[usize], mut width: W) -> Result<Vec<Solution<Vec<Vec<usize>>>>>
+    where
+        W: FnMut(&Solution<Vec<Vec<usize>>>),
+    {
+        // Keys only reorder the ciphertext, which is scored as plaintext
+        if self.cipher_encoding.encode_map != self.output_encoding.encode_map {
+            return Err(Error::Invalid("Transposition modes need the cipher alphabet to be the output alphabet".into()));
+        }
+        // Double columnar keys may have different widths
+        let widths = match kind {
+            Kind::DoubleColumnar => widths
+                .iter()
+                .flat_map(|&a| widths.iter().map(move |&b| vec![a, b]))
+                .collect::<Vec<_>>(),
+            _ => widths.iter().map(|&w| vec![w]).collect(),
+        };
+
+        let mut solutions = Vec::with_capacity(widths.len());
+        for key_widths in widths.into_iter() {
+            let cipher = ciphers::Columnar::new(kind, key_widths)?;
+            let climber = Climber::with_cipher(cipher, cipher_buf.clone(), self.output_encoding.clone(), self.output_encoding.clone());
+            let solution = self.climb(climber);
+            width(&solution);
+            solutions.push(solution);
+        }
+        solutions.sort_by(|a, b| b.energy.total_cmp(&a.energy));
+        solutions.truncate(self.leaderboard);
+        Ok(solutions)
+    }
+
+    /// Scores every route of the given widths, see `Route::all`. Returns the
+    /// best `leaderboard` routes with their energies.
+    pub fn routes(&self, cipher_buf: &[Char], widths: &[usize]) -> Result<Vec<(f64, Route, Transposition)>> {
+        // Routes are scored as plaintext
+        if self.cipher_encoding.encode_map != self.output_encoding.encode_map {
+            return Err(Error::Invalid(
+                "Route mode needs the cipher alphabet to be the output alphabet, climb substitutions with --transposition instead".into(),
+            ));
+        }
+        let mut output_buf = cipher_buf.to_vec();
+        let mut ranked = Route::all(cipher_buf.len(), widths)
+            .into_iter()
+            .map(|(route, transposition)| {
+                transposition.apply_to(cipher_buf, &mut output_buf);
+                (crate::energy(self.scorer.score(&output_buf), output_buf.len()), route, transposition)
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranked.truncate(self.leaderboard);
+        Ok(ranked)
+    }
+
+    /// Climbs substitution keys with the callbacks and schedule of `config`,
+    /// after the cribs at offsets of the ciphertext. Transpositions are
+    /// switched between in one climb when `anneal_transpositions`, otherwise
+    /// each is climbed on its own and numbered in the solutions.
+    #[allow(clippy::too_many_arguments)]
+    pub fn substitution<REPORT, ENERGY, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB, PROGRESS>(
+        &self,
+        climber: &Climber<Substitution>,
+        mut config: Config<&'a S, REPORT, ENERGY, Strategy, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        transpositions: Vec<Transposition>,
+        anneal_transpositions: bool,
+        cribs: &[(usize, String)],
+        method: &Method,
+        progress: PROGRESS,
+    ) -> Result<Leaderboard<Key>>
+    where
+        REPORT: Fn(&Climber<Substitution>, usize, usize, usize) + Sync,
+        ENERGY: Fn(f64, usize) -> f64 + Sync,
+        RANDOMKEY: Fn(&mut Climber<Substitution>, &mut Random) + Sync,
+        DERIVEKEY: Fn(&mut Climber<Substitution>, &mut Random) + Sync,
+        MUTATEKEY: Fn(&mut Climber<Substitution>, &mut Random) + Sync,
+        CRIB: Fn(&mut Climber<Substitution>) + Sync,
+        PROGRESS: Fn(Progress) + Sync,
+    {
+        if let Method::Tempering { exchange_cycle, .. } = *method {
+            // Every round climbs the chains for one exchange cycle
+            config.cycle = exchange_cycle;
+        }
+
+        // Either one climb switching between all transpositions, or one per transposition
+        let runs = if anneal_transpositions || transpositions.len() < 2 {
+            vec![transpositions]
+        } else {
+            transpositions.into_iter().map(|t| vec![t]).collect()
+        };
+        let mut leaderboard = Leaderboard::new(self.leaderboard);
+        for (run, transpositions) in runs.into_iter().enumerate() {
+            let mut climber = climber.clone();
+            climber.set_transpositions(transpositions)?;
+            for (offset, text) in cribs.iter() {
+                climber.crib_str(*offset, text)?;
+            }
+            let solutions = match *method {
+                Method::Restarts { threads, share } => {
+                    let parallel = Parallel {
+                        threads,
+                        restarts: self.restarts,
+                        seed: self.seed,
+                        leaderboard: self.leaderboard,
+                        share,
+                    };
+                    parallel.run(&climber, &config, |solution, leaderboard| progress(Progress::Restart(solution, leaderboard)))
+                }
+                Method::Tempering { chains, exchange_cycle } => {
+                    let (start, end) = (config.schedule.start, config.schedule.end);
+                    let (cold, hot) = (start.min(end), start.max(end));
+                    if cold.is_nan() || cold <= 0.0 {
+                        return Err(Error::Invalid("Parallel tempering needs positive temperatures".into()));
+                    }
+                    let tempering = Tempering {
+                        temperatures: Tempering::ladder(cold, hot, chains),
+                        rounds: (self.cycle / exchange_cycle).max(1),
+                        seed: self.seed,
+                        leaderboard: self.leaderboard,
+                    };
+                    let (solutions, rungs) = tempering.run(&climber, &config, |round, chains, _| progress(Progress::Round(round, chains)));
+                    progress(Progress::Rungs(&rungs));
+                    solutions
+                }
+                Method::Genetic(ref genetic) => {
+                    let genetic = Genetic {
+                        generations: self.cycle,
+                        seed: self.seed,
+                        leaderboard: self.leaderboard,
+                        ..genetic.clone()
+                    };
+                    genetic.run(&climber, &config, |generation, _| progress(Progress::Generation(generation)))
+                }
+            };
+            for mut solution in solutions.solutions {
+                solution.transposition += run;
+                leaderboard.offer(solution);
+            }
+        }
+        Ok(leaderboard)
+    }
+}
+
+/// Keys `encrypt` uses, random ones of `period` letters when no key words
+/// are given.
+#[derive(Debug, Clone)]
+pub struct Encryption {
+    pub mode: Mode,
+    /// Shift word, or comma separated key words of transpositions.
+    pub key: Option<String>,
+    pub period: usize,
+    /// Transposition applied before the cipher of the mode, see
+    /// `Transposition::parse`.
+    pub transposition: Option<String>,
+}
+
+/// A ciphertext and the key that encrypted it, as labelled lines.
+#[derive(Debug, Clone)]
+pub struct Encrypted {
+    pub cipher_buf: Vec<Char>,
+    pub key: Vec<(&'static str, String)>,
+}
+
+impl Encryption {
+    pub fn encrypt(
+        &self,
+        plain_buf: &[Char],
+        cipher_encoding: &Encoding,
+        output_encoding: &Encoding,
+        rng: &mut Random,
+    ) -> Result<Encrypted> {
+        let output_alphabet = output_encoding.base_alphabet();
+        let cipher_alphabet = cipher_encoding.base_alphabet();
+        if cipher_alphabet.len() < output_alphabet.len() {
+            return Err(Error::Invalid(
+                "The cipher alphabet must be at least as long as the output alphabet".into(),
+            ));
+        }
+
+        let mut output_buf = plain_buf.to_vec();
+        if let Some(spec) = &self.transposition {
+            let transposition = Transposition::parse(spec, output_buf.len()).map_err(Error::Invalid)?;
+            output_buf = transposition.inverse().apply(&output_buf);
+        }
+        let mut cipher_buf = vec![0; output_buf.len()];
+        let key = match self.mode {
+            Mode::Periodic(variant) => {
+                if cipher_alphabet.len() != output_alphabet.len() {
+                    return Err(Error::Invalid("Shift modes need alphabets of equal length".into()));
+                }
+                let shifts = match &self.key {
+                    Some(word) => output_encoding.encode_str(word)?,
+                    None => (0..self.period).map(|_| output_alphabet.random_char(rng)).collect(),
+                };
+                if shifts.is_empty() {
+                    return Err(Error::Invalid("Expected a key word of at least one character".into()));
+                }
+                let keys = shifts
+                    .iter()
+                    .map(|&s| variant.shift_key(s as usize, cipher_alphabet.len()).inverse(&output_alphabet))
+                    .collect::<Vec<_>>();
+                for (index, &c) in output_buf.iter().enumerate() {
+                    cipher_buf[index] = keys[index % keys.len()][c as usize][0];
+                }
+                vec![("Key", output_encoding.decode_str(&shifts))]
+            }
+            Mode::Route => {
+                if cipher_alphabet.len() != output_alphabet.len() {
+                    return Err(Error::Invalid("Transposition modes need alphabets of equal length".into()));
+                }
+                cipher_buf.copy_from_slice(&output_buf);
+                vec![]
+            }
+            Mode::Transposition(kind) => {
+                if cipher_alphabet.len() != output_alphabet.len() {
+                    return Err(Error::Invalid("Transposition modes need alphabets of equal length".into()));
+                }
+                let words = match &self.key {
+                    Some(words) => words.split(',').map(|w| output_encoding.encode_str(w)).collect::<error::Result<_>>()?,
+                    None => (0..kind.keys())
+                        .map(|_| (0..self.period).map(|_| output_alphabet.random_char(rng)).collect())
+                        .collect::<Vec<Vec<Char>>>(),
+                };
+                if words.len() != kind.keys() || words.iter().any(|w| w.is_empty()) {
+                    return Err(Error::Invalid(format!("Expected {} key word(s)", kind.keys())));
+                }
+                let ranks = words
+                    .iter()
+                    .map(|w| transposition::ranks_from_keyword(w, kind == Kind::Myszkowski))
+                    .collect::<Vec<_>>();
+                let transposition = kind.build(output_buf.len(), &ranks).map_err(|e| Error::Invalid(e.into()))?;
+                transposition.inverse().apply_to(&output_buf, &mut cipher_buf);
+                let words = words.iter().map(|w| output_encoding.decode_str(w)).collect::<Vec<_>>();
+                vec![("Key", words.join(","))]
+            }
+            Mode::Substitution => {
+                let key = Key::random(&cipher_alphabet, &output_alphabet, rng);
+                key.encode(&output_buf, &mut cipher_buf, &output_alphabet, rng)?;
+
+                let mut key_chars = vec![0; key.len()];
+                key.decode(&cipher_alphabet.char_vec(), &mut key_chars);
+                vec![
+                    ("Cipher", cipher_encoding.decode_str(&cipher_alphabet.char_vec())),
+                    ("Output", output_encoding.decode_str(&key_chars)),
+                ]
+            }
+        };
+        Ok(Encrypted { cipher_buf, key })
+    }
+}
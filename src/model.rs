@@ -0,0 +1,237 @@
+use crate::encoding::{Char, Encoding, Frequency, Table};
+use crate::stats::Smoothing;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+
+pub const MAGIC: &[u8; 4] = b"NGRM";
+pub const VERSION: u32 = 1;
+
+const DENSE: u8 = 0;
+const SPARSE: u8 = 1;
+
+/// Binary n-gram model file.
+///
+/// All numbers are little endian:
+///
+/// ```text
+/// magic     "NGRM"
+/// version   u32
+/// order     u32
+/// alphabet  u32 byte length, UTF-8 characters in encoding order
+/// smoothing u8 tag (0 inverse, 1 add-k, 2 good-turing, 3 kneser-ney), f64 parameter
+/// floor     f64
+/// table     u8 tag (0 dense, 1 sparse), u64 entry count, then
+///           dense:  f64 score per packed gram, then u64 words of the
+///                   bitset of seen grams
+///           sparse: order characters and f64 score per gram
+/// ```
+#[derive(Clone, Debug)]
+pub struct Model {
+    pub alphabet: String,
+    pub smoothing: Smoothing,
+    pub frequency: Frequency,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(invalid("Truncated model file"));
+        }
+        let slice = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(slice)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+}
+
+impl Model {
+    pub fn new(alphabet: &str, smoothing: Smoothing, frequency: Frequency) -> Self {
+        Self {
+            alphabet: alphabet.into(),
+            smoothing,
+            frequency,
+        }
+    }
+
+    /// Converts a `GRAM COUNT` text file into a model.
+    pub fn from_text_file(path: &str, n: usize, alphabet: &str, smoothing: Smoothing) -> crate::error::Result<Self> {
+        use crate::stats;
+        let encoding = Encoding::from_alphabet_string(alphabet);
+        let frequency = stats::Frequency::smoothed(
+            &stats::Count::from_file_with_encoding(path, n, &encoding)?,
+            smoothing,
+            encoding.encode_map.len(),
+        );
+        Ok(Self::new(alphabet, smoothing, Frequency::new(&frequency, &encoding)?))
+    }
+
+    /// Checks the magic bytes, so text and binary files can share a path option.
+    pub fn is_model_file(path: &str) -> bool {
+        use std::io::Read;
+        let mut magic = [0; 4];
+        std::fs::File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .map(|_| &magic == MAGIC)
+            .unwrap_or(false)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        Encoding::from_alphabet_string(&self.alphabet)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let frequency = &self.frequency;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(frequency.n as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.alphabet.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.alphabet.as_bytes());
+        let (tag, param) = match self.smoothing {
+            Smoothing::Inverse => (0u8, 0.0),
+            Smoothing::AddK(k) => (1, k),
+            Smoothing::GoodTuring => (2, 0.0),
+            Smoothing::KneserNey(discount) => (3, discount),
+        };
+        bytes.push(tag);
+        bytes.extend_from_slice(&param.to_le_bytes());
+        bytes.extend_from_slice(&frequency.floor.to_le_bytes());
+        match &frequency.table {
+            Table::Dense(dense) => {
+                bytes.push(DENSE);
+                bytes.extend_from_slice(&(dense.len() as u64).to_le_bytes());
+                bytes.reserve(dense.len() * 8);
+                for score in dense.iter() {
+                    bytes.extend_from_slice(&score.to_le_bytes());
+                }
+                for word in frequency.seen.iter() {
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+            }
+            Table::Sparse(sparse) => {
+                bytes.push(SPARSE);
+                bytes.extend_from_slice(&(sparse.len() as u64).to_le_bytes());
+                // Sorted, so equal models give equal files
+                let mut entries = sparse.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                for (gram, score) in entries {
+                    bytes.extend_from_slice(gram);
+                    bytes.extend_from_slice(&score.to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("Not a model file"));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(invalid(&format!("Unsupported model version {}", version)));
+        }
+        let n = reader.u32()? as usize;
+        let alphabet_len = reader.u32()? as usize;
+        let alphabet = std::str::from_utf8(reader.take(alphabet_len)?)
+            .map_err(|_| invalid("Invalid model alphabet"))?
+            .to_string();
+        let radix = alphabet.chars().count();
+        let tag = reader.u8()?;
+        let param = reader.f64()?;
+        let smoothing = match tag {
+            0 => Smoothing::Inverse,
+            1 => Smoothing::AddK(param),
+            2 => Smoothing::GoodTuring,
+            3 => Smoothing::KneserNey(param),
+            _ => return Err(invalid("Unknown model smoothing")),
+        };
+        let floor = reader.f64()?;
+        let kind = reader.u8()?;
+        let count = reader.u64()? as usize;
+        let mut seen = Vec::new();
+        let table = match kind {
+            DENSE => {
+                if Some(count) != radix.checked_pow(n as u32) {
+                    return Err(invalid("Dense table size does not match the alphabet"));
+                }
+                let scores = reader.take(count.checked_mul(8).ok_or_else(|| invalid("Truncated model file"))?)?;
+                let dense = scores
+                    .chunks_exact(8)
+                    .map(|chunk| {
+                        let mut buf = [0; 8];
+                        buf.copy_from_slice(chunk);
+                        f64::from_le_bytes(buf)
+                    })
+                    .collect::<Vec<f64>>();
+                seen = (0..count.div_ceil(64)).map(|_| reader.u64()).collect::<Result<Vec<_>>>()?;
+                Table::Dense(dense)
+            }
+            SPARSE => {
+                // Every entry takes its characters and a score
+                if count > reader.remaining() / (n + 8) {
+                    return Err(invalid("Truncated model file"));
+                }
+                let mut sparse = HashMap::with_capacity(count);
+                for _ in 0..count {
+                    let gram = reader.take(n)?.iter().map(|&c| c as Char).collect::<Vec<_>>();
+                    sparse.insert(gram, reader.f64()?);
+                }
+                Table::Sparse(sparse)
+            }
+            _ => return Err(invalid("Unknown model table")),
+        };
+        Ok(Self {
+            alphabet,
+            smoothing,
+            frequency: Frequency {
+                table,
+                n,
+                radix,
+                floor,
+                seen,
+            },
+        })
+    }
+
+    pub fn to_file(&self, path: &str) -> Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
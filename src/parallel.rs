@@ -0,0 +1,181 @@
+use crate::ciphers::Cipher;
+use crate::encoding::{Char, Scorer};
+use crate::hill::{Climber, Config};
+use crate::search::Search;
+use crate::util::{self, Random};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct Solution<K> {
+    pub restart: usize,
+    pub energy: f64,
+    /// Index of the climber's candidate transposition.
+    pub transposition: usize,
+    pub key: K,
+    pub output: Vec<Char>,
+}
+
+/// Best distinct solutions, ordered by descending energy.
+/// Solutions decoding to the same output only keep their best key.
+#[derive(Debug, Clone)]
+pub struct Leaderboard<K> {
+    pub size: usize,
+    pub solutions: Vec<Solution<K>>,
+}
+
+impl<K> Leaderboard<K> {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            solutions: Vec::with_capacity(size + 1),
+        }
+    }
+
+    pub fn best(&self) -> Option<&Solution<K>> {
+        self.solutions.first()
+    }
+
+    /// Inserts the solution if it ranks, returns whether it did.
+    pub fn offer(&mut self, solution: Solution<K>) -> bool {
+        if let Some(index) = self.solutions.iter().position(|s| s.output == solution.output) {
+            if self.solutions[index].energy >= solution.energy {
+                return false;
+            }
+            self.solutions.remove(index);
+        }
+        let index = self
+            .solutions
+            .iter()
+            .position(|s| s.energy < solution.energy)
+            .unwrap_or(self.solutions.len());
+        if index >= self.size {
+            return false;
+        }
+        self.solutions.insert(index, solution);
+        self.solutions.truncate(self.size);
+        true
+    }
+}
+
+/// Best energy, transposition and key found by any worker so far, updated
+/// by the climbers while they climb.
+#[derive(Debug)]
+pub struct SharedBest<K> {
+    best: Mutex<Option<(f64, usize, K)>>,
+}
+
+impl<K: Clone> SharedBest<K> {
+    pub fn new() -> Self {
+        Self { best: Mutex::new(None) }
+    }
+
+    /// Keeps the key if it beats the best energy, returns whether it did.
+    pub fn offer(&self, energy: f64, transposition: usize, key: &K) -> bool {
+        let mut best = self.best.lock().unwrap();
+        if best.as_ref().is_some_and(|(e, _, _)| *e >= energy) {
+            return false;
+        }
+        *best = Some((energy, transposition, key.clone()));
+        true
+    }
+
+    pub fn energy(&self) -> Option<f64> {
+        self.best.lock().unwrap().as_ref().map(|(energy, _, _)| *energy)
+    }
+
+    /// The best energy, transposition and key.
+    pub fn get(&self) -> Option<(f64, usize, K)> {
+        self.best.lock().unwrap().clone()
+    }
+}
+
+impl<K: Clone> Default for SharedBest<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Derives the seed of a single restart, so results do not depend on
+/// which worker happened to pick the restart up.
+pub fn restart_seed(seed: u64, restart: usize) -> u64 {
+    seed.wrapping_add((restart as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// Runs restarts of a `Climber` on worker threads, which share the best
+/// key found so far.
+#[derive(Debug, Clone)]
+pub struct Parallel {
+    pub threads: usize,
+    pub restarts: usize,
+    pub seed: u64,
+    pub leaderboard: usize,
+    /// Probability that a restart climbs from the shared best key, mutated,
+    /// instead of a random key. Results then depend on the timing of the
+    /// workers unless there is one thread.
+    pub share: f64,
+}
+
+impl Parallel {
+    pub fn run<C, SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB, FINISH>(
+        &self,
+        climber: &Climber<C>,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        finish: FINISH,
+    ) -> Leaderboard<C::Key>
+    where
+        C: Cipher + Sync,
+        SCORER: Scorer + Sync,
+        REPORT: Fn(&Climber<C>, usize, usize, usize) + Sync,
+        ENERGY: Fn(f64, usize) -> f64 + Sync,
+        SEARCH: Search + Sync,
+        RANDOMKEY: Fn(&mut Climber<C>, &mut Random) + Sync,
+        DERIVEKEY: Fn(&mut Climber<C>, &mut Random) + Sync,
+        MUTATEKEY: Fn(&mut Climber<C>, &mut Random) + Sync,
+        CRIB: Fn(&mut Climber<C>) + Sync,
+        FINISH: Fn(&Solution<C::Key>, &Leaderboard<C::Key>) + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let shared = Mutex::new(Leaderboard::new(self.leaderboard));
+        let best = Arc::new(SharedBest::new());
+        let threads = self.threads.max(1).min(self.restarts.max(1));
+
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let restart = next.fetch_add(1, Ordering::Relaxed);
+                    if restart >= self.restarts {
+                        break;
+                    }
+                    let mut rng = util::seeded_random(restart_seed(self.seed, restart));
+                    let mut worker = climber.clone();
+                    worker.shared = Some(best.clone());
+                    match best.get() {
+                        Some((_, transposition, key)) if util::probability(self.share, &mut rng) => {
+                            worker.transpose(transposition);
+                            worker.run_key = key;
+                            (config.mutate_key)(&mut worker, &mut rng);
+                            worker.cipher.constrain(&mut worker.run_key);
+                            worker.resume(config, config.schedule, &mut rng);
+                        }
+                        _ => worker.climb(config, &mut rng),
+                    }
+                    worker.decode_top();
+
+                    let solution = Solution {
+                        restart,
+                        energy: worker.top_energy,
+                        transposition: worker.top_transposition,
+                        key: worker.top_key,
+                        output: worker.output_buf,
+                    };
+                    let mut leaderboard = shared.lock().unwrap();
+                    leaderboard.offer(solution.clone());
+                    finish(&solution, &leaderboard);
+                });
+            }
+        });
+
+        shared.into_inner().unwrap()
+    }
+}
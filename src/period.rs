@@ -0,0 +1,165 @@
+use crate::analyze::ENGLISH_IOC;
+use crate::encoding::Char;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Largest period considered when none is given.
+pub const MAX_PERIOD: usize = 20;
+
+/// Shortest repeated sequence counted by `kasiski_distances`.
+pub const KASISKI_MIN_LEN: usize = 3;
+
+/// Raw index of coincidence of the counts of every distinct symbol.
+pub fn ioc_of_counts<I: IntoIterator<Item = usize>>(counts: I) -> f64 {
+    let (mut numer, mut total) = (0, 0);
+    for count in counts {
+        numer += count * count.saturating_sub(1);
+        total += count;
+    }
+    if total < 2 {
+        return 0.0;
+    }
+    numer as f64 / (total * (total - 1)) as f64
+}
+
+/// Raw index of coincidence of a sequence of symbols, or of any items such
+/// as the bigrams of digraphic indices.
+pub fn ioc<T: Hash + Eq, I: IntoIterator<Item = T>>(items: I) -> f64 {
+    let mut counts = HashMap::new();
+    for item in items {
+        *counts.entry(item).or_insert(0usize) += 1;
+    }
+    ioc_of_counts(counts.into_values())
+}
+
+/// Average column IoC of every period in `1..=max_period`, index 0 is
+/// period 1. Periods leaving columns shorter than two symbols are skipped.
+pub fn ioc_scan(cipher: &[Char], max_period: usize) -> Vec<(usize, f64)> {
+    (1..=max_period.min(cipher.len() / 2))
+        .map(|period| {
+            let average = (0..period)
+                .map(|column| ioc(cipher.iter().copied().skip(column).step_by(period)))
+                .sum::<f64>()
+                / period as f64;
+            (period, average)
+        })
+        .collect()
+}
+
+/// Distances between repeated sequences of at least `KASISKI_MIN_LEN`
+/// symbols, one per pair of neighbouring occurrences. Longer repeats are
+/// only counted once, by their first `KASISKI_MIN_LEN` symbols.
+pub fn kasiski_distances(cipher: &[Char]) -> Vec<usize> {
+    let mut last: HashMap<&[Char], usize> = HashMap::new();
+    let mut distances = Vec::new();
+    for (index, gram) in cipher.windows(KASISKI_MIN_LEN).enumerate() {
+        if let Some(previous) = last.insert(gram, index) {
+            // Skip the shifted copies of a longer repeat
+            let longer = index > 0 && previous > 0 && cipher[index - 1] == cipher[previous - 1];
+            if !longer {
+                distances.push(index - previous);
+            }
+        }
+    }
+    distances
+}
+
+/// Share of Kasiski distances divisible by each period in `2..=max_period`,
+/// relative to the `1 / period` share expected by chance.
+pub fn kasiski_votes(distances: &[usize], max_period: usize) -> Vec<(usize, f64)> {
+    (2..=max_period)
+        .map(|period| {
+            if distances.is_empty() {
+                return (period, 0.0);
+            }
+            let votes = distances.iter().filter(|&&d| d % period == 0).count();
+            (period, votes as f64 / distances.len() as f64 * period as f64)
+        })
+        .collect()
+}
+
+/// Greatest common divisor of all distances, 0 if there are none.
+pub fn gcd(distances: &[usize]) -> usize {
+    fn gcd2(a: usize, b: usize) -> usize {
+        if b == 0 {
+            a
+        } else {
+            gcd2(b, a % b)
+        }
+    }
+    distances.iter().fold(0, |g, &d| gcd2(g, d))
+}
+
+/// Friedman's period estimate from the IoC of the whole text, for a
+/// plaintext alphabet of `symbols` letters.
+pub fn friedman(cipher: &[Char], symbols: usize) -> f64 {
+    let n = cipher.len() as f64;
+    let kappa_r = 1.0 / symbols as f64;
+    let kappa_o = ioc(cipher.iter().copied());
+    let denominator = (n - 1.0) * kappa_o - kappa_r * n + ENGLISH_IOC;
+    if denominator <= 0.0 {
+        return f64::INFINITY;
+    }
+    (ENGLISH_IOC - kappa_r) * n / denominator
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub period: usize,
+    /// Share of the total score, all candidates sum to 1.
+    pub confidence: f64,
+    pub ioc: f64,
+    pub kasiski: f64,
+}
+
+/// Ranks the periods `1..=max_period` of a periodic polyalphabetic cipher
+/// by their column IoC, Kasiski votes and closeness to the Friedman
+/// estimate. Multiples of a period that explain the text as well are
+/// ranked below it.
+pub fn estimate(cipher: &[Char], symbols: usize, max_period: usize) -> Vec<Candidate> {
+    let scan = ioc_scan(cipher, max_period);
+    let distances = kasiski_distances(cipher);
+    let votes = kasiski_votes(&distances, max_period);
+    let friedman = friedman(cipher, symbols);
+    let random = 1.0 / symbols as f64;
+
+    // How far each period lifts the column IoC from random towards English
+    let lift = scan
+        .iter()
+        .map(|&(_, ioc)| ((ioc - random) / (ENGLISH_IOC - random)).clamp(0.0, 1.5))
+        .collect::<Vec<_>>();
+
+    let mut candidates = scan
+        .iter()
+        .map(|&(period, ioc)| {
+            let kasiski = votes.get(period.wrapping_sub(2)).map_or(0.0, |v| v.1);
+            let closeness = if friedman.is_finite() {
+                (-(period as f64 - friedman).abs() / friedman.max(1.0)).exp()
+            } else {
+                0.0
+            };
+            let mut score = lift[period - 1].powi(4) * (1.0 + 0.5 * kasiski.min(4.0) + closeness);
+            // A divisor lifting nearly as much explains the text with fewer columns
+            let divisor_lift = (1..period)
+                .filter(|d| period % d == 0)
+                .map(|d| lift[d - 1])
+                .fold(0.0, f64::max);
+            if divisor_lift > 0.9 * lift[period - 1] {
+                score *= 0.25;
+            }
+            Candidate {
+                period,
+                confidence: score,
+                ioc,
+                kasiski,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let total = candidates.iter().map(|c| c.confidence).sum::<f64>();
+    for candidate in candidates.iter_mut() {
+        candidate.confidence = if total > 0.0 { candidate.confidence / total } else { 0.0 };
+    }
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates
+}
@@ -0,0 +1,57 @@
+use crate::encoding::Char;
+use crate::key::Key;
+
+/// Key family of a periodic polyalphabetic cipher.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    /// Any substitution per column.
+    General,
+    /// Shifted alphabets, `c = p + k`.
+    Vigenere,
+    /// Reversed shifted alphabets, `c = k - p`.
+    Beaufort,
+    /// Inverse shifts, `c = p - k`.
+    VariantBeaufort,
+}
+
+impl std::str::FromStr for Variant {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "periodic" => Ok(Variant::General),
+            "vigenere" => Ok(Variant::Vigenere),
+            "beaufort" => Ok(Variant::Beaufort),
+            "variant" => Ok(Variant::VariantBeaufort),
+            _ => Err(format!("Unknown periodic variant: {}", s)),
+        }
+    }
+}
+
+impl Variant {
+    pub fn is_shift(self) -> bool {
+        self != Variant::General
+    }
+
+    /// Builds the decoding key of a single column shifted by `shift`.
+    pub fn shift_key(self, shift: usize, len: usize) -> Key {
+        let mut key = Key::new(len);
+        for c in 0..len {
+            let p = match self {
+                Variant::General | Variant::Vigenere => (c + len - shift) % len,
+                Variant::Beaufort => (shift + len - c) % len,
+                Variant::VariantBeaufort => (c + shift) % len,
+            };
+            key[c] = p as Char;
+        }
+        key
+    }
+
+    /// The shift of a key built by `shift_key`.
+    pub fn shift_of(self, key: &Key) -> usize {
+        let len = key.len();
+        match self {
+            Variant::General | Variant::Vigenere => (len - key[0] as usize) % len,
+            Variant::Beaufort | Variant::VariantBeaufort => key[0] as usize,
+        }
+    }
+}
@@ -5,6 +5,12 @@ pub struct Pre {
     pub emit: HashSet<char>,
 }
 
+impl Default for Pre {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Pre {
     pub fn new() -> Self {
         Self {
@@ -23,7 +29,7 @@ impl Pre {
     pub fn process(&self, input: &str) -> String {
         let mut output = String::new();
         for c in input.chars().map(|c| *self.normalize.get(&c).unwrap_or(&c)) {
-            if self.emit.get(&c).is_some() {
+            if self.emit.contains(&c) {
                 output.push(c)
             }
         }
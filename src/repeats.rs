@@ -0,0 +1,123 @@
+use crate::encoding::Char;
+use crate::route::{Orientation, Path, Route};
+use crate::util::Random;
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
+
+/// Counts bigrams `(cipher[i], cipher[i + period])` equal to an earlier one.
+///
+/// Homophonic ciphers hide repeats at period 1 but keep them at the skip
+/// distance of a transposition, Z340's famous peak is at period 19.
+pub fn bigram_repeats(cipher: &[Char], period: usize) -> usize {
+    if period == 0 || period >= cipher.len() {
+        return 0;
+    }
+    let pairs = cipher.len() - period;
+    let distinct = (0..pairs)
+        .map(|i| (cipher[i], cipher[i + period]))
+        .collect::<HashSet<_>>()
+        .len();
+    pairs - distinct
+}
+
+/// Bigram repeats for every period in `1..=max_period`, index 0 is period 1.
+pub fn repeats_table(cipher: &[Char], max_period: usize) -> Vec<usize> {
+    (1..=max_period).map(|period| bigram_repeats(cipher, period)).collect()
+}
+
+/// Mean and standard deviation of repeats over shuffled ciphertexts, which
+/// keep the symbol counts and lose every positional pattern.
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    pub samples: usize,
+    pub mean: Vec<f64>,
+    pub deviation: Vec<f64>,
+}
+
+impl Baseline {
+    pub fn shuffled(cipher: &[Char], max_period: usize, samples: usize, rng: &mut Random) -> Self {
+        let mut sum = vec![0.0; max_period];
+        let mut squares = vec![0.0; max_period];
+        let mut shuffled = cipher.to_vec();
+        for _ in 0..samples {
+            shuffled.shuffle(rng);
+            for (index, count) in repeats_table(&shuffled, max_period).into_iter().enumerate() {
+                sum[index] += count as f64;
+                squares[index] += (count * count) as f64;
+            }
+        }
+        let samples_f = samples.max(1) as f64;
+        let mean = sum.iter().map(|s| s / samples_f).collect::<Vec<_>>();
+        let deviation = squares
+            .iter()
+            .zip(mean.iter())
+            .map(|(s, m)| (s / samples_f - m * m).max(0.0).sqrt())
+            .collect();
+        Self {
+            samples,
+            mean,
+            deviation,
+        }
+    }
+
+    /// Standard deviations of `count` above the baseline at `period`.
+    pub fn z_score(&self, period: usize, count: usize) -> f64 {
+        let (mean, deviation) = (self.mean[period - 1], self.deviation[period - 1]);
+        if deviation == 0.0 {
+            return 0.0;
+        }
+        (count as f64 - mean) / deviation
+    }
+}
+
+/// Bigram repeats of a readout at every period, with the baseline of its
+/// own shuffles.
+#[derive(Debug, Clone)]
+pub struct Readout {
+    pub route: Route,
+    /// See `repeats_table`.
+    pub repeats: Vec<usize>,
+    pub baseline: Baseline,
+}
+
+impl Readout {
+    pub fn z_score(&self, period: usize) -> f64 {
+        self.baseline.z_score(period, self.repeats[period - 1])
+    }
+
+    /// The period with the highest z-score and its score.
+    pub fn peak(&self) -> (usize, f64) {
+        (1..=self.repeats.len())
+            .map(|period| (period, self.z_score(period)))
+            .fold((1, f64::NEG_INFINITY), |best, next| if next.1 > best.1 { next } else { best })
+    }
+}
+
+/// Reads the ciphertext along the diagonals of a grid of `width` columns,
+/// down right and down left with one or two rows per step, and tables the
+/// bigram repeats of every readout up to `max_period` against `samples`
+/// shuffles. Routes reading the same order as an earlier one are skipped.
+pub fn diagonal_readouts(cipher: &[Char], width: usize, max_period: usize, samples: usize, rng: &mut Random) -> Vec<Readout> {
+    let mut seen = HashSet::new();
+    let mut readouts = Vec::new();
+    for &orientation in [Orientation::Identity, Orientation::Mirror].iter() {
+        for dy in 1..=2 {
+            for dx in 1..width {
+                let route = Route::new(Path::Diagonal(dx, dy), width, orientation);
+                let transposition = match route.transposition(cipher.len()) {
+                    Ok(transposition) => transposition,
+                    Err(_) => continue,
+                };
+                if seen.insert(transposition.indices().to_vec()) {
+                    let readout = transposition.apply(cipher);
+                    readouts.push(Readout {
+                        route,
+                        repeats: repeats_table(&readout, max_period),
+                        baseline: Baseline::shuffled(&readout, max_period, samples, rng),
+                    });
+                }
+            }
+        }
+    }
+    readouts
+}
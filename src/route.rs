@@ -0,0 +1,268 @@
+use crate::transposition::Transposition;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Reading order over a grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Path {
+    Rows,
+    Columns,
+    /// Rows in alternating directions.
+    Boustrophedon,
+    /// Clockwise from the top left corner inwards.
+    Spiral,
+    /// Steps `dx` columns and `dy` rows, wrapping around both edges.
+    Diagonal(usize, usize),
+    /// Steps one row down and two columns right, carrying into the next
+    /// row: Z340's period 19 in its 17 column grid.
+    Knight,
+}
+
+/// Symmetry applied to the grid before it is read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Identity,
+    /// Columns reversed.
+    Mirror,
+    /// Rows reversed.
+    Flip,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Rows and columns exchanged.
+    Transpose,
+    AntiTranspose,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 8] = [
+        Orientation::Identity,
+        Orientation::Mirror,
+        Orientation::Flip,
+        Orientation::Rotate90,
+        Orientation::Rotate180,
+        Orientation::Rotate270,
+        Orientation::Transpose,
+        Orientation::AntiTranspose,
+    ];
+
+    const NAMES: [&'static str; 8] =
+        ["identity", "mirror", "flip", "rotate90", "rotate180", "rotate270", "transpose", "antitranspose"];
+
+    fn swaps_sides(self) -> bool {
+        matches!(
+            self,
+            Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Transpose | Orientation::AntiTranspose
+        )
+    }
+
+    /// Maps a cell of the oriented grid back to the `width` x `height` grid.
+    fn cell(self, row: usize, column: usize, width: usize, height: usize) -> (usize, usize) {
+        let (last_row, last_column) = (height - 1, width - 1);
+        match self {
+            Orientation::Identity => (row, column),
+            Orientation::Mirror => (row, last_column - column),
+            Orientation::Flip => (last_row - row, column),
+            Orientation::Rotate90 => (last_row - column, row),
+            Orientation::Rotate180 => (last_row - row, last_column - column),
+            Orientation::Rotate270 => (column, last_column - row),
+            Orientation::Transpose => (column, row),
+            Orientation::AntiTranspose => (last_row - column, last_column - row),
+        }
+    }
+}
+
+/// Reads a text written row by row into a grid of `width` columns along
+/// `path`, after orienting the grid. The last row may be incomplete, its
+/// missing cells are skipped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Route {
+    pub path: Path,
+    pub width: usize,
+    pub orientation: Orientation,
+}
+
+impl Route {
+    pub fn new(path: Path, width: usize, orientation: Orientation) -> Self {
+        Self {
+            path,
+            width,
+            orientation,
+        }
+    }
+
+    /// Cells of a `width` x `height` grid in reading order, row major.
+    fn walk(&self, width: usize, height: usize) -> Vec<usize> {
+        let cells = width * height;
+        let mut order = Vec::with_capacity(cells);
+        match self.path {
+            Path::Rows => order.extend(0..cells),
+            Path::Columns => {
+                for column in 0..width {
+                    order.extend((0..height).map(|row| row * width + column));
+                }
+            }
+            Path::Boustrophedon => {
+                for row in 0..height {
+                    let start = row * width;
+                    if row % 2 == 0 {
+                        order.extend(start..start + width);
+                    } else {
+                        order.extend((start..start + width).rev());
+                    }
+                }
+            }
+            Path::Spiral => {
+                let (mut top, mut bottom, mut left, mut right) = (0, height, 0, width);
+                while top < bottom && left < right {
+                    order.extend((left..right).map(|c| top * width + c));
+                    order.extend((top + 1..bottom).map(|r| r * width + right - 1));
+                    if top + 1 < bottom {
+                        order.extend((left..right - 1).rev().map(|c| (bottom - 1) * width + c));
+                    }
+                    if left + 1 < right {
+                        order.extend((top + 1..bottom - 1).rev().map(|r| r * width + left));
+                    }
+                    top += 1;
+                    bottom -= 1;
+                    left += 1;
+                    right -= 1;
+                }
+            }
+            Path::Diagonal(dx, dy) => {
+                walk_cells(cells, &mut order, |cell| {
+                    let (row, column) = (cell / width, cell % width);
+                    (row + dy) % height * width + (column + dx) % width
+                });
+            }
+            Path::Knight => walk_cells(cells, &mut order, |cell| (cell + width + 2) % cells),
+        }
+        order
+    }
+
+    pub fn transposition(&self, len: usize) -> Result<Transposition, &'static str> {
+        if self.width == 0 {
+            return Err("Transposition width may not be zero");
+        }
+        if len == 0 {
+            return Ok(Transposition::new());
+        }
+        let (width, height) = (self.width, len.div_ceil(self.width));
+        let (oriented_width, oriented_height) = match self.orientation.swaps_sides() {
+            true => (height, width),
+            false => (width, height),
+        };
+        let indices = self
+            .walk(oriented_width, oriented_height)
+            .into_iter()
+            .map(|cell| {
+                let (row, column) = (cell / oriented_width, cell % oriented_width);
+                let (row, column) = self.orientation.cell(row, column, width, height);
+                row * width + column
+            })
+            .filter(|&index| index < len)
+            .collect();
+        Transposition::from_indices(indices)
+    }
+
+    /// Every route of the given widths, skipping those that read the same
+    /// order as an earlier one.
+    pub fn all(len: usize, widths: &[usize]) -> Vec<(Route, Transposition)> {
+        let mut seen = HashSet::new();
+        let mut routes = Vec::new();
+        for &width in widths.iter() {
+            let height = len.div_ceil(width.max(1));
+            let mut paths = vec![Path::Rows, Path::Columns, Path::Boustrophedon, Path::Spiral, Path::Knight];
+            for dy in 0..height.min(3) {
+                paths.extend((0..width).filter(|&dx| dx + dy > 0).map(|dx| Path::Diagonal(dx, dy)));
+            }
+            for &path in paths.iter() {
+                for &orientation in Orientation::ALL.iter() {
+                    let route = Route::new(path, width, orientation);
+                    if let Ok(transposition) = route.transposition(len) {
+                        if seen.insert(transposition.indices().to_vec()) {
+                            routes.push((route, transposition));
+                        }
+                    }
+                }
+            }
+        }
+        routes
+    }
+}
+
+/// Follows `next` from cell 0, restarting at the first unvisited cell
+/// whenever the walk returns to a visited one.
+fn walk_cells<F>(cells: usize, order: &mut Vec<usize>, next: F)
+where
+    F: Fn(usize) -> usize,
+{
+    let mut visited = vec![false; cells];
+    let mut start = 0;
+    let mut cell = 0;
+    for _ in 0..cells {
+        if visited[cell] {
+            while visited[start] {
+                start += 1;
+            }
+            cell = start;
+        }
+        visited[cell] = true;
+        order.push(cell);
+        cell = next(cell);
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path {
+            Path::Rows => write!(f, "rows:{}", self.width)?,
+            Path::Columns => write!(f, "columns:{}", self.width)?,
+            Path::Boustrophedon => write!(f, "boustrophedon:{}", self.width)?,
+            Path::Spiral => write!(f, "spiral:{}", self.width)?,
+            Path::Diagonal(dx, dy) => write!(f, "diagonal:{}:{}:{}", self.width, dx, dy)?,
+            Path::Knight => write!(f, "knight:{}", self.width)?,
+        }
+        if self.orientation != Orientation::Identity {
+            let index = Orientation::ALL.iter().position(|&o| o == self.orientation).unwrap();
+            write!(f, "+{}", Orientation::NAMES[index])?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `NAME:WIDTH[:DX:DY][+ORIENTATION]`, as printed by `Display`.
+impl FromStr for Route {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (spec, orientation) = match s.split_once('+') {
+            Some((spec, name)) => {
+                let index = Orientation::NAMES
+                    .iter()
+                    .position(|&n| n == name)
+                    .ok_or_else(|| format!("Unknown orientation: {}", name))?;
+                (spec, Orientation::ALL[index])
+            }
+            None => (s, Orientation::Identity),
+        };
+        let mut args = spec.split(':');
+        let name = args.next().unwrap_or_default();
+        let args = args
+            .map(|a| a.parse::<usize>().map_err(|_| format!("Invalid route argument: {}", a)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (path, width) = match (name, args.as_slice()) {
+            ("rows", &[width]) => (Path::Rows, width),
+            ("columns", &[width]) => (Path::Columns, width),
+            ("boustrophedon", &[width]) => (Path::Boustrophedon, width),
+            ("spiral", &[width]) => (Path::Spiral, width),
+            ("diagonal", &[width, dx, dy]) => (Path::Diagonal(dx, dy), width),
+            ("knight", &[width]) => (Path::Knight, width),
+            _ => return Err(format!("Unknown route: {}", s)),
+        };
+        if width == 0 {
+            return Err(format!("Invalid route width: {}", s));
+        }
+        Ok(Route::new(path, width, orientation))
+    }
+}
@@ -0,0 +1,202 @@
+use crate::search::Search;
+
+/// Temperatures are multiplied or divided by this when adapting to the
+/// acceptance rate.
+const ADAPT_FACTOR: f64 = 0.9;
+
+/// Log10 range of the temperatures searched by `Schedule::calibrated`.
+const CALIBRATION_RANGE: (f64, f64) = (-6.0, 12.0);
+
+/// How the temperature changes over the cycles of a climb. The end
+/// temperature is above the start for searches accepting less at higher
+/// temperatures, see `acceptance_rises`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cooling {
+    /// Keeps the start temperature.
+    Constant,
+    /// Moves linearly to the end temperature at the last cycle.
+    Linear,
+    /// Changes by the same factor every cycle, reaching the end temperature
+    /// at the last cycle.
+    Geometric,
+    /// `start / (1 + c ln(1 + cycle))`, with `c` reaching the end
+    /// temperature at the last cycle.
+    Logarithmic,
+    /// Moves towards the end temperature while the share of accepted moves
+    /// of a cycle is above a target falling linearly from the given rate to
+    /// 0, back towards the start while below.
+    Adaptive(f64),
+    /// Geometric, back to the start temperature after the given
+    /// number of cycles without a new best energy.
+    Reheat(usize),
+}
+
+/// Parses `constant`, `linear`, `geometric`, `logarithmic`,
+/// `adaptive[:RATE]` or `reheat[:PATIENCE]`.
+impl std::str::FromStr for Cooling {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, param) = match s.split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (s, None),
+        };
+        let invalid = || format!("Invalid parameter: {}", s);
+        match (name, param) {
+            ("constant", None) => Ok(Cooling::Constant),
+            ("linear", None) => Ok(Cooling::Linear),
+            ("geometric", None) => Ok(Cooling::Geometric),
+            ("logarithmic", None) => Ok(Cooling::Logarithmic),
+            ("adaptive", param) => {
+                let rate = param.map_or(Ok(0.5), |p| p.parse::<f64>().map_err(|_| invalid()))?;
+                if !(0.0..=1.0).contains(&rate) {
+                    return Err(invalid());
+                }
+                Ok(Cooling::Adaptive(rate))
+            }
+            ("reheat", param) => {
+                let patience = param.map_or(Ok(50), |p| p.parse::<usize>().map_err(|_| invalid()))?;
+                Ok(Cooling::Reheat(patience.max(1)))
+            }
+            _ => Err(format!("Unknown schedule: {}", s)),
+        }
+    }
+}
+
+/// Whether the search accepts the losses more often at higher temperatures,
+/// as Metropolis does and Lavy's acceptance does not. `None` when there are
+/// no losses or the search ignores the temperature.
+pub fn acceptance_rises<S: Search>(search: &S, losses: &[f64]) -> Option<bool> {
+    let (low, high) = CALIBRATION_RANGE;
+    match acceptance_rate(search, losses, high).partial_cmp(&acceptance_rate(search, losses, low))? {
+        std::cmp::Ordering::Greater => Some(true),
+        std::cmp::Ordering::Less => Some(false),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// Mean acceptance of the losses at the temperature `10^log_temperature`.
+fn acceptance_rate<S: Search>(search: &S, losses: &[f64], log_temperature: f64) -> f64 {
+    let temperature = 10f64.powf(log_temperature);
+    losses.iter().map(|&loss| search.acceptance(loss, temperature)).sum::<f64>() / losses.len().max(1) as f64
+}
+
+/// Temperature of a climb, from `start` at the first cycle towards `end`.
+/// Climbers copy the schedule at the start of every climb and update it
+/// after every cycle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    pub cooling: Cooling,
+    pub start: f64,
+    pub end: f64,
+    current: f64,
+    stagnant: usize,
+}
+
+impl Schedule {
+    pub fn new(cooling: Cooling, start: f64, end: f64) -> Self {
+        Self {
+            cooling,
+            start,
+            end,
+            current: start,
+            stagnant: 0,
+        }
+    }
+
+    pub fn constant(temperature: f64) -> Self {
+        Self::new(Cooling::Constant, temperature, temperature)
+    }
+
+    /// End temperature when none is given: a hundredth of the start, or a
+    /// hundred times the start for searches whose acceptance falls as the
+    /// temperature rises.
+    pub fn default_end<S: Search>(search: &S, start: f64) -> f64 {
+        match acceptance_rises(search, &[1.0]) {
+            Some(false) => start * 100.0,
+            _ => start / 100.0,
+        }
+    }
+
+    /// Picks the start and end temperatures at which the search accepts the
+    /// shares `start_rate` and `end_rate` of the sampled losses, see
+    /// `Climber::sample_losses`. `None` when there are no losses or the
+    /// search ignores the temperature.
+    pub fn calibrated<S: Search>(
+        cooling: Cooling,
+        search: &S,
+        losses: &[f64],
+        start_rate: f64,
+        end_rate: f64,
+    ) -> Option<Self> {
+        if losses.is_empty() {
+            return None;
+        }
+        let rate = |log_temperature: f64| acceptance_rate(search, losses, log_temperature);
+        let increasing = acceptance_rises(search, losses)?;
+        let temperature = |target: f64| {
+            let (mut low, mut high) = CALIBRATION_RANGE;
+            for _ in 0..100 {
+                let middle = (low + high) / 2.0;
+                if (rate(middle) < target) == increasing {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            10f64.powf((low + high) / 2.0)
+        };
+        Some(Self::new(cooling, temperature(start_rate), temperature(end_rate)))
+    }
+
+    /// Temperature of the running cycle.
+    pub fn temperature(&self) -> f64 {
+        self.current
+    }
+
+    /// Goes back to the start temperature.
+    pub fn reset(&mut self) {
+        self.current = self.start;
+        self.stagnant = 0;
+    }
+
+    /// Sets the temperature of the cycle after `cycle` of `cycles`, given the
+    /// share of moves accepted during `cycle` and whether it found a new
+    /// best energy.
+    pub fn update(&mut self, cycle: usize, cycles: usize, acceptance: f64, improved: bool) {
+        let cycles = cycles.max(1);
+        let progress = ((cycle + 1) as f64 / cycles as f64).min(1.0);
+        // Per cycle factor of geometric cooling, 0 when it cannot reach the end
+        let factor = if self.start > 0.0 && self.end > 0.0 {
+            (self.end / self.start).powf(1.0 / cycles as f64)
+        } else {
+            0.0
+        };
+        self.current = match self.cooling {
+            Cooling::Constant => self.start,
+            Cooling::Linear => self.start + (self.end - self.start) * progress,
+            Cooling::Geometric => self.current * factor,
+            Cooling::Logarithmic if self.end <= 0.0 => 0.0,
+            Cooling::Logarithmic => {
+                let c = (self.start / self.end - 1.0) / (1.0 + cycles as f64).ln();
+                self.start / (1.0 + c * (2.0 + cycle as f64).ln())
+            }
+            Cooling::Adaptive(rate) => {
+                let towards_end = if self.end <= self.start { ADAPT_FACTOR } else { 1.0 / ADAPT_FACTOR };
+                if acceptance > rate * (1.0 - progress) {
+                    self.current * towards_end
+                } else {
+                    self.current / towards_end
+                }
+            }
+            Cooling::Reheat(patience) => {
+                self.stagnant = if improved { 0 } else { self.stagnant + 1 };
+                if self.stagnant >= patience {
+                    self.stagnant = 0;
+                    self.start
+                } else {
+                    self.current * factor
+                }
+            }
+        };
+    }
+}
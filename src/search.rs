@@ -0,0 +1,437 @@
+use crate::encoding::Char;
+use crate::hill::MIN_ENERGY;
+use crate::util::{self, Random};
+use std::collections::HashMap;
+
+/// Decides which neighbouring keys a climb moves to. Climbers clone the
+/// search at the start of every climb, so it may keep state between moves.
+pub trait Search: Clone {
+    /// Resets the search before a climb starting at `energy`.
+    fn start(&mut self, _energy: f64) {}
+
+    /// Whether to move from the `current` energy to a neighbour of energy
+    /// `next`, at the temperature of the running cycle.
+    fn accept(&mut self, current: f64, next: f64, temperature: f64, rng: &mut Random) -> bool;
+
+    /// Start temperature suited to the search when none is configured, 0
+    /// when it ignores the temperature.
+    fn temperature(&self) -> f64 {
+        0.0
+    }
+
+    /// Probability of accepting a neighbour losing `loss > 0` at
+    /// `temperature`, used to calibrate temperatures. 0 when the search
+    /// ignores the temperature.
+    fn acceptance(&self, _loss: f64, _temperature: f64) -> f64 {
+        0.0
+    }
+
+    /// Whether every move of a round is scored before taking the best one,
+    /// instead of accepting or rejecting the moves one by one.
+    fn steepest(&self) -> bool {
+        false
+    }
+
+    /// Whether the search keeps a tabu list. Steepest climbers then only
+    /// take moves it `allowed`, and report them with `moved`, see
+    /// `Cipher::assignments`.
+    fn tabu(&self) -> bool {
+        false
+    }
+
+    /// Whether a move to a neighbour of energy `next`, making the given
+    /// `(cipher symbol, plaintext)` assignments, may be taken.
+    fn allowed(&self, _assignments: &[(usize, Char)], _next: f64) -> bool {
+        true
+    }
+
+    /// Records a taken move, which replaced the given assignments.
+    fn moved(&mut self, _replaced: &[(usize, Char)]) {}
+}
+
+/// Moves to the best neighbour of each round while it improves.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SteepestAscent;
+
+impl Search for SteepestAscent {
+    fn accept(&mut self, current: f64, next: f64, _temperature: f64, _rng: &mut Random) -> bool {
+        next > current
+    }
+
+    fn steepest(&self) -> bool {
+        true
+    }
+}
+
+/// Moves to the first improving neighbour.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FirstImprovement;
+
+impl Search for FirstImprovement {
+    fn accept(&mut self, current: f64, next: f64, _temperature: f64, _rng: &mut Random) -> bool {
+        next > current
+    }
+}
+
+/// Lavy's acceptance, the historical default of the climber. Worse
+/// neighbours are accepted with probability `exp(d / temperature) - 1`
+/// for a loss `d`, when that exceeds `threshold`.
+#[derive(Debug, Clone, Copy)]
+pub struct Lavy {
+    pub threshold: f64,
+}
+
+impl Default for Lavy {
+    fn default() -> Self {
+        Self { threshold: 0.0085 }
+    }
+}
+
+impl Search for Lavy {
+    fn accept(&mut self, current: f64, next: f64, temperature: f64, rng: &mut Random) -> bool {
+        if next > current {
+            return true;
+        }
+        let degradation = next - current;
+        let p = (-degradation / temperature).exp() - 1.0;
+        p > self.threshold && util::probability(p, rng)
+    }
+
+    fn temperature(&self) -> f64 {
+        3500.0
+    }
+
+    fn acceptance(&self, loss: f64, temperature: f64) -> f64 {
+        let p = (loss / temperature).exp() - 1.0;
+        if p > self.threshold {
+            p.min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Metropolis acceptance: worse neighbours are accepted with probability
+/// `exp(-d / temperature)` for a loss `d`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Annealing;
+
+impl Search for Annealing {
+    fn accept(&mut self, current: f64, next: f64, temperature: f64, rng: &mut Random) -> bool {
+        next > current || util::probability(((next - current) / temperature).exp(), rng)
+    }
+
+    fn temperature(&self) -> f64 {
+        5.0
+    }
+
+    fn acceptance(&self, loss: f64, temperature: f64) -> f64 {
+        (-loss / temperature).exp()
+    }
+}
+
+/// Accepts every neighbour losing less than the temperature.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThresholdAccepting;
+
+impl Search for ThresholdAccepting {
+    fn accept(&mut self, current: f64, next: f64, temperature: f64, _rng: &mut Random) -> bool {
+        next > current - temperature
+    }
+
+    fn temperature(&self) -> f64 {
+        10.0
+    }
+
+    fn acceptance(&self, loss: f64, temperature: f64) -> f64 {
+        if loss < temperature {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Record-to-record travel: accepts every neighbour within `deviation` of
+/// the best energy of the climb.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordToRecord {
+    pub deviation: f64,
+    record: f64,
+}
+
+impl RecordToRecord {
+    pub fn new(deviation: f64) -> Self {
+        Self {
+            deviation,
+            record: MIN_ENERGY,
+        }
+    }
+}
+
+impl Default for RecordToRecord {
+    fn default() -> Self {
+        Self::new(20.0)
+    }
+}
+
+impl Search for RecordToRecord {
+    fn start(&mut self, energy: f64) {
+        self.record = energy;
+    }
+
+    fn accept(&mut self, current: f64, next: f64, _temperature: f64, _rng: &mut Random) -> bool {
+        if next <= current && next <= self.record - self.deviation {
+            return false;
+        }
+        self.record = self.record.max(next);
+        true
+    }
+}
+
+/// Late acceptance hill climbing: accepts neighbours no worse than the
+/// current energy or than the energy `length` decisions ago.
+#[derive(Debug, Clone)]
+pub struct LateAcceptance {
+    pub length: usize,
+    history: Vec<f64>,
+    index: usize,
+}
+
+impl LateAcceptance {
+    pub fn new(length: usize) -> Self {
+        Self {
+            length: length.max(1),
+            history: Vec::new(),
+            index: 0,
+        }
+    }
+}
+
+impl Default for LateAcceptance {
+    fn default() -> Self {
+        Self::new(200)
+    }
+}
+
+impl Search for LateAcceptance {
+    fn start(&mut self, energy: f64) {
+        self.history.clear();
+        self.history.resize(self.length, energy);
+        self.index = 0;
+    }
+
+    fn accept(&mut self, current: f64, next: f64, _temperature: f64, _rng: &mut Random) -> bool {
+        if self.history.is_empty() {
+            self.start(current);
+        }
+        let late = self.history[self.index];
+        let accepted = next >= current || next >= late;
+        self.history[self.index] = if accepted { next } else { current };
+        self.index = (self.index + 1) % self.history.len();
+        accepted
+    }
+}
+
+/// Tabu search: moves to the best allowed neighbour of every round, even
+/// when it is worse. The assignments replaced by a move are tabu for the
+/// next `tenure` moves, unless a move back reaches a new best energy.
+#[derive(Debug, Clone)]
+pub struct Tabu {
+    pub tenure: usize,
+    /// Number of moves after which each tabu assignment is allowed again.
+    expiry: HashMap<(usize, Char), usize>,
+    moves: usize,
+    best: f64,
+}
+
+impl Tabu {
+    pub fn new(tenure: usize) -> Self {
+        Self {
+            tenure,
+            expiry: HashMap::new(),
+            moves: 0,
+            best: MIN_ENERGY,
+        }
+    }
+}
+
+impl Default for Tabu {
+    fn default() -> Self {
+        Self::new(15)
+    }
+}
+
+impl Search for Tabu {
+    fn start(&mut self, energy: f64) {
+        self.expiry.clear();
+        self.moves = 0;
+        self.best = energy;
+    }
+
+    fn accept(&mut self, _current: f64, next: f64, _temperature: f64, _rng: &mut Random) -> bool {
+        self.best = self.best.max(next);
+        true
+    }
+
+    fn steepest(&self) -> bool {
+        true
+    }
+
+    fn tabu(&self) -> bool {
+        true
+    }
+
+    fn allowed(&self, assignments: &[(usize, Char)], next: f64) -> bool {
+        next > self.best
+            || assignments
+                .iter()
+                .all(|assignment| self.expiry.get(assignment).is_none_or(|&expiry| expiry <= self.moves))
+    }
+
+    fn moved(&mut self, replaced: &[(usize, Char)]) {
+        self.moves += 1;
+        for &assignment in replaced.iter() {
+            self.expiry.insert(assignment, self.moves + self.tenure);
+        }
+    }
+}
+
+/// A search selected by name, see `FromStr`.
+#[derive(Debug, Clone)]
+pub enum Strategy {
+    SteepestAscent(SteepestAscent),
+    FirstImprovement(FirstImprovement),
+    Lavy(Lavy),
+    Annealing(Annealing),
+    ThresholdAccepting(ThresholdAccepting),
+    RecordToRecord(RecordToRecord),
+    LateAcceptance(LateAcceptance),
+    Tabu(Tabu),
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::Lavy(Lavy::default())
+    }
+}
+
+/// Parses `steepest`, `first`, `lavy[:P]`, `anneal`, `threshold`,
+/// `record[:D]`, `late[:L]` or `tabu[:T]`.
+impl std::str::FromStr for Strategy {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or_default();
+        let params = parts
+            .map(|p| p.parse::<f64>().map_err(|_| format!("Invalid parameter: {}", p)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if params.iter().any(|p| p.is_nan() || *p < 0.0) {
+            return Err(format!("Invalid parameters: {}", s));
+        }
+        if matches!(name, "late" | "tabu") && params.iter().any(|p| p.fract() != 0.0) {
+            return Err(format!("Invalid parameters: {}", s));
+        }
+        let max_params = match name {
+            "lavy" | "record" | "late" | "tabu" => 1,
+            _ => 0,
+        };
+        if params.len() > max_params {
+            return Err(format!("Too many parameters: {}", s));
+        }
+        let param = |index: usize, default: f64| params.get(index).copied().unwrap_or(default);
+        match name {
+            "steepest" => Ok(Strategy::SteepestAscent(SteepestAscent)),
+            "first" => Ok(Strategy::FirstImprovement(FirstImprovement)),
+            "lavy" => Ok(Strategy::Lavy(Lavy {
+                threshold: param(0, Lavy::default().threshold),
+            })),
+            "anneal" => Ok(Strategy::Annealing(Annealing)),
+            "threshold" => Ok(Strategy::ThresholdAccepting(ThresholdAccepting)),
+            "record" => Ok(Strategy::RecordToRecord(RecordToRecord::new(param(
+                0,
+                RecordToRecord::default().deviation,
+            )))),
+            "late" => Ok(Strategy::LateAcceptance(LateAcceptance::new(
+                param(0, LateAcceptance::default().length as f64) as usize,
+            ))),
+            "tabu" => Ok(Strategy::Tabu(Tabu::new(param(0, Tabu::default().tenure as f64) as usize))),
+            _ => Err(format!("Unknown search: {}", s)),
+        }
+    }
+}
+
+impl Search for Strategy {
+    fn start(&mut self, energy: f64) {
+        match self {
+            Strategy::SteepestAscent(search) => search.start(energy),
+            Strategy::FirstImprovement(search) => search.start(energy),
+            Strategy::Lavy(search) => search.start(energy),
+            Strategy::Annealing(search) => search.start(energy),
+            Strategy::ThresholdAccepting(search) => search.start(energy),
+            Strategy::RecordToRecord(search) => search.start(energy),
+            Strategy::LateAcceptance(search) => search.start(energy),
+            Strategy::Tabu(search) => search.start(energy),
+        }
+    }
+
+    fn accept(&mut self, current: f64, next: f64, temperature: f64, rng: &mut Random) -> bool {
+        match self {
+            Strategy::SteepestAscent(search) => search.accept(current, next, temperature, rng),
+            Strategy::FirstImprovement(search) => search.accept(current, next, temperature, rng),
+            Strategy::Lavy(search) => search.accept(current, next, temperature, rng),
+            Strategy::Annealing(search) => search.accept(current, next, temperature, rng),
+            Strategy::ThresholdAccepting(search) => search.accept(current, next, temperature, rng),
+            Strategy::RecordToRecord(search) => search.accept(current, next, temperature, rng),
+            Strategy::LateAcceptance(search) => search.accept(current, next, temperature, rng),
+            Strategy::Tabu(search) => search.accept(current, next, temperature, rng),
+        }
+    }
+
+    fn temperature(&self) -> f64 {
+        match self {
+            Strategy::SteepestAscent(search) => search.temperature(),
+            Strategy::FirstImprovement(search) => search.temperature(),
+            Strategy::Lavy(search) => search.temperature(),
+            Strategy::Annealing(search) => search.temperature(),
+            Strategy::ThresholdAccepting(search) => search.temperature(),
+            Strategy::RecordToRecord(search) => search.temperature(),
+            Strategy::LateAcceptance(search) => search.temperature(),
+            Strategy::Tabu(search) => search.temperature(),
+        }
+    }
+
+    fn acceptance(&self, loss: f64, temperature: f64) -> f64 {
+        match self {
+            Strategy::SteepestAscent(search) => search.acceptance(loss, temperature),
+            Strategy::FirstImprovement(search) => search.acceptance(loss, temperature),
+            Strategy::Lavy(search) => search.acceptance(loss, temperature),
+            Strategy::Annealing(search) => search.acceptance(loss, temperature),
+            Strategy::ThresholdAccepting(search) => search.acceptance(loss, temperature),
+            Strategy::RecordToRecord(search) => search.acceptance(loss, temperature),
+            Strategy::LateAcceptance(search) => search.acceptance(loss, temperature),
+            Strategy::Tabu(search) => search.acceptance(loss, temperature),
+        }
+    }
+
+    fn steepest(&self) -> bool {
+        matches!(self, Strategy::SteepestAscent(_) | Strategy::Tabu(_))
+    }
+
+    fn tabu(&self) -> bool {
+        matches!(self, Strategy::Tabu(_))
+    }
+
+    fn allowed(&self, assignments: &[(usize, Char)], next: f64) -> bool {
+        match self {
+            Strategy::Tabu(search) => search.allowed(assignments, next),
+            _ => true,
+        }
+    }
+
+    fn moved(&mut self, replaced: &[(usize, Char)]) {
+        if let Strategy::Tabu(search) = self {
+            search.moved(replaced);
+        }
+    }
+}
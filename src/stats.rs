@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use crate::encoding::Encoding;
+use crate::error::{Error, Result};
 use crate::pre::Pre;
 
 pub struct NGrams<'a, T> {
@@ -49,9 +51,15 @@ impl Count {
     }
 
     pub fn add(&mut self, input: &str) {
-        if input.len() >= self.n {
-            for i in 0..=(input.len() - self.n) {
-                let gram = &input[i..(i + self.n)];
+        // Byte offsets of every character, grams may span multi-byte ones
+        let bounds = input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(input.len()))
+            .collect::<Vec<_>>();
+        if bounds.len() > self.n {
+            for i in 0..bounds.len() - self.n {
+                let gram = &input[bounds[i]..bounds[i + self.n]];
                 match self.map.get_mut(gram) {
                     Some(count) => {
                         *count += 1;
@@ -64,26 +72,178 @@ impl Count {
         }
     }
 
-    pub fn from_file(path: &str, n: usize) -> Self {
+    /// Counts the grams of a corpus after running it through `pre`.
+    /// Grams span line breaks, the corpus is read line by line.
+    pub fn from_corpus(path: &str, n: usize, pre: &Pre) -> Result<Self> {
         use std::fs::File;
         use std::io::{BufRead, BufReader};
+        if n == 0 {
+            return Err(Error::Invalid("Gram order may not be zero".into()));
+        }
         let mut count = Self::new(n);
-        let file = File::open(path).unwrap();
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
         let reader = BufReader::new(file);
+        let mut carry = String::new();
         for line in reader.lines() {
-            let line = line.unwrap();
+            let line = line.map_err(|e| Error::io(path, e))?;
+            carry.push_str(&pre.process(&line));
+            count.add(&carry);
+            let keep = carry.chars().count().min(n - 1);
+            let skip = carry.chars().count() - keep;
+            carry = carry.chars().skip(skip).collect();
+        }
+        Ok(count)
+    }
+
+    /// Drops grams counted fewer than `min` times.
+    pub fn prune(&mut self, min: u64) {
+        self.map.retain(|_, count| *count >= min);
+    }
+
+    /// Keeps only the `top` most frequent grams.
+    pub fn truncate(&mut self, top: usize) {
+        let ordered = self.ordered();
+        if ordered.len() > top {
+            for (gram, _) in ordered[top..].iter() {
+                self.map.remove(gram);
+            }
+        }
+    }
+
+    /// Grams by descending count, ties broken alphabetically.
+    pub fn ordered(&self) -> Vec<(String, u64)> {
+        let mut output = self
+            .map
+            .iter()
+            .map(|(gram, &count)| (gram.clone(), count))
+            .collect::<Vec<_>>();
+        output.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        output
+    }
+
+    /// Writes `GRAM COUNT` lines by descending count, as read by `from_file`.
+    pub fn to_file(&self, path: &str) -> Result<()> {
+        use std::io::Write;
+        let write = || {
+            let file = std::fs::File::create(path)?;
+            let mut writer = std::io::BufWriter::new(file);
+            for (gram, count) in self.ordered() {
+                writeln!(writer, "{} {}", gram, count)?;
+            }
+            writer.flush()
+        };
+        write().map_err(|e| Error::io(path, e))
+    }
+
+    /// Reads `GRAM COUNT` lines, every gram of `n` characters. Empty lines
+    /// are skipped.
+    pub fn from_file(path: &str, n: usize) -> Result<Self> {
+        Self::read(path, n, None)
+    }
+
+    /// Like `from_file`, rejecting the lines of grams outside the encoding.
+    pub fn from_file_with_encoding(path: &str, n: usize, encoding: &Encoding) -> Result<Self> {
+        Self::read(path, n, Some(encoding))
+    }
+
+    fn read(path: &str, n: usize, encoding: Option<&Encoding>) -> Result<Self> {
+        use std::fs::File;
+        use std::io::{BufRead, BufReader};
+        if n == 0 {
+            return Err(Error::Invalid("Gram order may not be zero".into()));
+        }
+        let mut count = Self::new(n);
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        let reader = BufReader::new(file);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| Error::io(path, e))?;
             let words = line.split_whitespace().collect::<Vec<_>>();
-            assert!(words[0].len() == n);
-            count.map.insert(words[0].into(), words[1].parse::<u64>().unwrap());
+            let (gram, value) = match words.as_slice() {
+                [] => continue,
+                [gram, value] => (*gram, *value),
+                _ => return Err(Error::parse(path, index + 1, "expected GRAM COUNT")),
+            };
+            if gram.chars().count() != n {
+                let message = format!("gram {:?} is not of order {}", gram, n);
+                return Err(Error::parse(path, index + 1, message));
+            }
+            if let Some(encoding) = encoding {
+                if let Err(Error::UnknownSymbol { symbol, .. }) = encoding.encode_str(gram) {
+                    return Err(Error::parse(path, index + 1, format!("unknown symbol {:?} in gram {:?}", symbol, gram)));
+                }
+            }
+            let value = value
+                .parse::<u64>()
+                .map_err(|_| Error::parse(path, index + 1, format!("invalid count {:?}", value)))?;
+            count.map.insert(gram.into(), value);
         }
-        count
+        Ok(count)
     }
 }
 
+/// How n-gram counts are turned into scores.
+///
+/// `AddK` and `GoodTuring` score the log10 probability of the whole gram,
+/// `KneserNey` the log10 probability of its last character given the rest,
+/// so a text scores its chain rule log-likelihood.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    /// Legacy `1 / -log10(p)` scores with a floor of `0.1 / total`.
+    Inverse,
+    /// Log10 probabilities, adding `k` to the count of every possible gram.
+    AddK(f64),
+    /// Log10 probabilities, simple Good-Turing discounting of rare counts.
+    GoodTuring,
+    /// Log10 probabilities, interpolated Kneser-Ney with the given discount.
+    KneserNey(f64),
+}
+
+impl std::str::FromStr for Smoothing {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, param) = match s.split_once(':') {
+            Some((name, param)) => {
+                let param = param.parse::<f64>().map_err(|_| format!("Invalid parameter: {}", param))?;
+                (name, Some(param))
+            }
+            None => (s, None),
+        };
+        match (name, param) {
+            ("inverse", None) => Ok(Smoothing::Inverse),
+            ("add-k", Some(k)) if !(k > 0.0 && k.is_finite()) => Err(format!("Invalid parameter: {}, k must be positive", s)),
+            ("add-k", param) => Ok(Smoothing::AddK(param.unwrap_or(1.0))),
+            ("good-turing", None) => Ok(Smoothing::GoodTuring),
+            ("kneser-ney", Some(d)) if !(d > 0.0 && d < 1.0) => Err(format!("Invalid parameter: {}, the discount must be between 0 and 1", s)),
+            ("kneser-ney", param) => Ok(Smoothing::KneserNey(param.unwrap_or(0.75))),
+            ("inverse", Some(_)) | ("good-turing", Some(_)) => Err(format!("Unexpected parameter: {}", s)),
+            _ => Err(format!("Unknown smoothing: {}", s)),
+        }
+    }
+}
+
+/// The gram without its last character.
+fn context(gram: &str) -> &str {
+    gram.char_indices().next_back().map_or(gram, |(index, _)| &gram[..index])
+}
+
+/// The gram without its first character.
+fn suffix(gram: &str) -> &str {
+    gram.chars().next().map_or(gram, |c| &gram[c.len_utf8()..])
+}
+
+/// Lower order model used for grams missing from a `Frequency`.
+pub struct Backoff {
+    /// Log10 weight of the lower order model per seen context.
+    pub weights: HashMap<String, f64>,
+    /// Model of order `n - 1` over the gram without its first character.
+    pub lower: Frequency,
+}
+
 pub struct Frequency {
     pub map: HashMap<String, f64>,
     pub n: usize,
     pub floor: f64,
+    pub backoff: Option<Box<Backoff>>,
 }
 
 impl Frequency {
@@ -92,6 +252,7 @@ impl Frequency {
             map: Default::default(),
             n,
             floor,
+            backoff: None,
         }
     }
 
@@ -106,12 +267,114 @@ impl Frequency {
         frequency
     }
 
+    /// Scores the counted grams of an alphabet with `alphabet` characters.
+    pub fn smoothed(count: &Count, smoothing: Smoothing, alphabet: usize) -> Self {
+        match smoothing {
+            Smoothing::Inverse => Self::from_count(count),
+            Smoothing::AddK(k) => Self::add_k(count, k, alphabet),
+            Smoothing::GoodTuring => Self::good_turing(count, alphabet),
+            Smoothing::KneserNey(discount) => {
+                let counts = count.map.iter().map(|(k, &v)| (k.clone(), v as f64)).collect();
+                Self::kneser_ney(&counts, count.n, discount, alphabet)
+            }
+        }
+    }
+
+    fn possible(n: usize, alphabet: usize) -> f64 {
+        (alphabet as f64).powi(n as i32)
+    }
+
+    fn add_k(count: &Count, k: f64, alphabet: usize) -> Self {
+        let denom = count.total() as f64 + k * Self::possible(count.n, alphabet);
+        let mut frequency = Self::new(count.n, (k / denom).log10());
+        for (key, &val) in count.map.iter() {
+            frequency.map.insert(key.clone(), ((val as f64 + k) / denom).log10());
+        }
+        frequency
+    }
+
+    fn good_turing(count: &Count, alphabet: usize) -> Self {
+        // Counts above this are reliable and left undiscounted
+        const RELIABLE: u64 = 5;
+        let total = count.total() as f64;
+        let mut counts_of_counts: HashMap<u64, f64> = HashMap::new();
+        for &val in count.map.values() {
+            *counts_of_counts.entry(val).or_insert(0.0) += 1.0;
+        }
+        let n_r = |r: u64| *counts_of_counts.get(&r).unwrap_or(&0.0);
+        let unseen = (Self::possible(count.n, alphabet) - count.map.len() as f64).max(1.0);
+        let unseen_mass = n_r(1).max(1.0) / total;
+        let mut frequency = Self::new(count.n, (unseen_mass / unseen).log10());
+        for (key, &val) in count.map.iter() {
+            let adjusted = if val <= RELIABLE && n_r(val + 1) > 0.0 {
+                (val + 1) as f64 * n_r(val + 1) / n_r(val)
+            } else {
+                val as f64
+            };
+            frequency.map.insert(key.clone(), (adjusted / total).log10());
+        }
+        frequency
+    }
+
+    fn kneser_ney(counts: &HashMap<String, f64>, n: usize, discount: f64, alphabet: usize) -> Self {
+        if n == 1 {
+            // Interpolate with the uniform distribution
+            let total: f64 = counts.values().sum();
+            let uniform = discount * counts.len() as f64 / total / alphabet as f64;
+            let mut frequency = Self::new(1, uniform.log10());
+            for (key, &val) in counts.iter() {
+                let p = (val - discount).max(0.0) / total + uniform;
+                frequency.map.insert(key.clone(), p.log10());
+            }
+            return frequency;
+        }
+
+        // Lower orders count the distinct characters preceding a gram
+        let mut context_totals: HashMap<&str, (f64, f64)> = HashMap::new();
+        let mut continuation: HashMap<String, f64> = HashMap::new();
+        for (key, &val) in counts.iter() {
+            let context = context_totals.entry(context(key)).or_insert((0.0, 0.0));
+            context.0 += val;
+            context.1 += 1.0;
+            *continuation.entry(suffix(key).into()).or_insert(0.0) += 1.0;
+        }
+        let lower = Self::kneser_ney(&continuation, n - 1, discount, alphabet);
+
+        let mut frequency = Self::new(n, lower.floor);
+        let mut weights = HashMap::with_capacity(context_totals.len());
+        for (&context, &(total, types)) in context_totals.iter() {
+            weights.insert(context.into(), (discount * types / total).log10());
+        }
+        for (key, &val) in counts.iter() {
+            let (total, types) = context_totals[context(key)];
+            let lower_p = 10f64.powf(lower.get(suffix(key)));
+            let p = (val - discount).max(0.0) / total + discount * types / total * lower_p;
+            frequency.map.insert(key.clone(), p.log10());
+        }
+        frequency.backoff = Some(Box::new(Backoff { weights, lower }));
+        frequency
+    }
+
+    /// Scores a gram, backing off to lower orders where available.
+    pub fn get(&self, gram: &str) -> f64 {
+        if let Some(&score) = self.map.get(gram) {
+            return score;
+        }
+        match &self.backoff {
+            Some(backoff) => {
+                let weight = backoff.weights.get(context(gram)).unwrap_or(&0.0);
+                weight + backoff.lower.get(suffix(gram))
+            }
+            None => self.floor,
+        }
+    }
+
     pub fn ordered(&self) -> Vec<(String, f64)> {
         let mut output = Vec::new();
         for (key, &val) in self.map.iter() {
             output.push((key.into(), val));
         }
-        output.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        output.sort_by(|a, b| b.1.total_cmp(&a.1));
         output
     }
 }
@@ -120,23 +383,23 @@ impl Frequency {
 pub struct Dictionary(pub HashSet<String>);
 
 impl Dictionary {
-    pub fn from_file(path: &str, pre: &Pre) -> Self {
+    pub fn from_file(path: &str, pre: &Pre) -> Result<Self> {
         use std::fs::File;
         use std::io::{BufRead, BufReader};
         let mut dict = HashSet::new();
-        let file = File::open(path).unwrap();
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
         let reader = BufReader::new(file);
         for line in reader.lines() {
-            let line = line.unwrap();
+            let line = line.map_err(|e| Error::io(path, e))?;
             let word = pre.process(&line);
-            dict.insert(word.into());
+            dict.insert(word);
         }
-        Self(dict)
+        Ok(Self(dict))
     }
 }
 
 use std::collections::HashSet;
-pub fn find_words<'a>(text: &'a str, dict: &HashSet<String>) -> Vec<(usize, usize)> {
+pub fn find_words(text: &str, dict: &HashSet<String>) -> Vec<(usize, usize)> {
     let max_len = 10;
     let mut words = Vec::new();
     for i in 0..text.len() {
@@ -0,0 +1,137 @@
+use crate::ciphers::Cipher;
+use crate::encoding::Scorer;
+use crate::hill::{Climber, Config};
+use crate::parallel::{restart_seed, Leaderboard, Solution};
+use crate::schedule::{self, Schedule};
+use crate::search::Search;
+use crate::util::{self, Random};
+
+/// Exchanges of a rung with the next hotter one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rung {
+    pub temperature: f64,
+    pub attempts: usize,
+    pub swaps: usize,
+}
+
+impl Rung {
+    /// Share of the proposed exchanges that were accepted.
+    pub fn acceptance(&self) -> f64 {
+        self.swaps as f64 / self.attempts.max(1) as f64
+    }
+}
+
+/// Replica exchange: runs one chain per temperature on its own thread and,
+/// between rounds, exchanges the chains of neighbouring temperatures by the
+/// Metropolis criterion. Hot chains cross barriers and hand their keys down
+/// to the cold ones, so the search should accept more at higher
+/// temperatures, as `Annealing` does.
+#[derive(Debug, Clone)]
+pub struct Tempering {
+    /// Positive temperatures of the rungs, coldest first.
+    pub temperatures: Vec<f64>,
+    /// Exchange rounds, each climbing `config.cycle` cycles on every rung.
+    pub rounds: usize,
+    pub seed: u64,
+    pub leaderboard: usize,
+}
+
+impl Tempering {
+    /// `rungs` temperatures spaced geometrically from `cold` to `hot`.
+    pub fn ladder(cold: f64, hot: f64, rungs: usize) -> Vec<f64> {
+        if rungs < 2 {
+            return vec![cold; rungs];
+        }
+        let factor = (hot / cold).powf(1.0 / (rungs - 1) as f64);
+        (0..rungs).map(|rung| cold * factor.powi(rung as i32)).collect()
+    }
+
+    /// Whether the search accepts more at higher temperatures, as the
+    /// exchange criterion assumes.
+    pub fn supports<S: Search>(search: &S) -> bool {
+        schedule::acceptance_rises(search, &[1.0]) == Some(true)
+    }
+
+    /// Climbs the chains for all rounds, calling `round` after the exchanges
+    /// of every round. Returns the best keys of the chains and the exchanges
+    /// of every rung.
+    ///
+    /// # Panics
+    ///
+    /// When the search is not `supports`ed.
+    pub fn run<C, SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB, ROUND>(
+        &self,
+        climber: &Climber<C>,
+        config: &Config<SCORER, REPORT, ENERGY, SEARCH, RANDOMKEY, DERIVEKEY, MUTATEKEY, CRIB>,
+        mut round: ROUND,
+    ) -> (Leaderboard<C::Key>, Vec<Rung>)
+    where
+        C: Cipher + Send,
+        SCORER: Scorer + Sync,
+        REPORT: Fn(&Climber<C>, usize, usize, usize) + Sync,
+        ENERGY: Fn(f64, usize) -> f64 + Sync,
+        SEARCH: Search + Sync,
+        RANDOMKEY: Fn(&mut Climber<C>, &mut Random) + Sync,
+        DERIVEKEY: Fn(&mut Climber<C>, &mut Random) + Sync,
+        MUTATEKEY: Fn(&mut Climber<C>, &mut Random) + Sync,
+        CRIB: Fn(&mut Climber<C>) + Sync,
+        ROUND: FnMut(usize, &[Climber<C>], &[Rung]),
+    {
+        assert!(Self::supports(&config.search), "Tempering: the search must accept more at higher temperatures");
+        let count = self.temperatures.len();
+        let mut chains = vec![climber.clone(); count];
+        let mut rngs = (0..count)
+            .map(|rung| util::seeded_random(restart_seed(self.seed, rung)))
+            .collect::<Vec<_>>();
+        let mut rng = util::seeded_random(restart_seed(self.seed, count));
+        let mut rungs = self
+            .temperatures
+            .iter()
+            .map(|&temperature| Rung {
+                temperature,
+                attempts: 0,
+                swaps: 0,
+            })
+            .collect::<Vec<_>>();
+
+        for index in 0..self.rounds {
+            std::thread::scope(|scope| {
+                for (rung, (chain, rng)) in chains.iter_mut().zip(rngs.iter_mut()).enumerate() {
+                    let schedule = Schedule::constant(self.temperatures[rung]);
+                    scope.spawn(move || {
+                        if index == 0 {
+                            chain.randomize(config, rng);
+                        }
+                        chain.resume(config, schedule, rng);
+                    });
+                }
+            });
+
+            // Alternate between the even and the odd pairs of rungs
+            for cold in (index % 2..count.saturating_sub(1)).step_by(2) {
+                let hot = cold + 1;
+                let gain = (chains[hot].run_energy - chains[cold].run_energy)
+                    * (1.0 / self.temperatures[cold] - 1.0 / self.temperatures[hot]);
+                rungs[cold].attempts += 1;
+                if gain >= 0.0 || util::probability(gain.exp(), &mut rng) {
+                    chains.swap(cold, hot);
+                    rungs[cold].swaps += 1;
+                }
+            }
+            round(index, &chains, &rungs);
+        }
+
+        let mut leaderboard = Leaderboard::new(self.leaderboard);
+        for (rung, mut chain) in chains.into_iter().enumerate() {
+            chain.decode_top();
+            leaderboard.offer(Solution {
+                restart: rung,
+                energy: chain.top_energy,
+                transposition: chain.top_transposition,
+                key: chain.top_key,
+                output: chain.output_buf,
+            });
+        }
+        (leaderboard, rungs)
+    }
+}
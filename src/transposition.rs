@@ -0,0 +1,318 @@
+use crate::encoding::Char;
+use crate::route::Route;
+use std::collections::HashSet;
+
+/// Reordering of a text, `output[i] = input[self[i]]`.
+///
+/// Transpositions built here decrypt, they map every plaintext position
+/// to its position in the ciphertext. Use `inverse` to encrypt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transposition(Vec<usize>);
+
+impl Default for Transposition {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transposition {
+    pub fn new() -> Self {
+        Self(Default::default())
+    }
+
+    pub fn identity(len: usize) -> Self {
+        Self((0..len).collect())
+    }
+
+    pub fn from_mapper<F>(len: usize, map: F) -> Result<Self, &'static str>
+    where
+        F: Fn(usize) -> usize,
+    {
+        Self((0..len).map(map).collect()).validate()
+    }
+
+    pub fn from_indices(indices: Vec<usize>) -> Result<Self, &'static str> {
+        Self(indices).validate()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn indices(&self) -> &[usize] {
+        &self.0
+    }
+
+    pub fn apply_to<T: Copy>(&self, input: &[T], output: &mut [T]) {
+        for index in 0..input.len() {
+            output[index] = input[self.0[index]];
+        }
+    }
+
+    pub fn apply<T: Copy>(&self, input: &[T]) -> Vec<T> {
+        self.0.iter().map(|&index| input[index]).collect()
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut indices = vec![0; self.0.len()];
+        for (index, &from) in self.0.iter().enumerate() {
+            indices[from] = index;
+        }
+        Self(indices)
+    }
+
+    /// Applying the result equals applying `self`, then `next`.
+    pub fn chain(&self, next: &Self) -> Self {
+        Self(next.0.iter().map(|&index| self.0[index]).collect())
+    }
+
+    pub fn validate(self) -> Result<Self, &'static str> {
+        let len = self.0.len();
+        let lhs = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, _)| i)
+            .collect::<HashSet<usize>>();
+        let rhs = self.0.iter().copied().collect::<HashSet<usize>>();
+        if rhs.len() != len {
+            return Err("Duplicate transposition indices");
+        }
+        if !lhs.difference(&rhs).collect::<HashSet<_>>().is_empty() {
+            return Err("Mismatched transposition indices");
+        }
+        if !rhs.difference(&lhs).collect::<HashSet<_>>().is_empty() {
+            return Err("Mismatched transposition indices");
+        }
+        for index in 0..len {
+            if !lhs.contains(&index) {
+                return Err("Missing transposition indices");
+            }
+            if !rhs.contains(&index) {
+                return Err("Missing transposition indices");
+            }
+        }
+        Ok(self)
+    }
+
+    /// Myszkowski transposition: the plaintext is written in rows of
+    /// `ranks.len()` columns and read off by ascending column rank. Columns
+    /// sharing a rank are read together, row by row. Incomplete last rows
+    /// are allowed.
+    pub fn myszkowski(len: usize, ranks: &[usize]) -> Result<Self, &'static str> {
+        if ranks.is_empty() {
+            return Err("Transposition width may not be zero");
+        }
+        let mut indices = vec![0; len];
+        read_off(ranks, &(0..len).collect::<Vec<_>>(), &mut indices);
+        Self(indices).validate()
+    }
+
+    /// Columnar transposition, columns are read off in ascending rank.
+    pub fn columnar(len: usize, ranks: &[usize]) -> Result<Self, &'static str> {
+        let mut sorted = ranks.to_vec();
+        sorted.sort_unstable();
+        if sorted.iter().enumerate().any(|(i, &r)| i != r) {
+            return Err("Columnar ranks must be a permutation");
+        }
+        Self::myszkowski(len, ranks)
+    }
+
+    /// Double columnar transposition, encrypted with `first` then `second`.
+    pub fn double_columnar(
+        len: usize,
+        first: &[usize],
+        second: &[usize],
+    ) -> Result<Self, &'static str> {
+        Ok(Self::columnar(len, second)?.chain(&Self::columnar(len, first)?))
+    }
+
+    /// Reads every `period`th character, wrapping around the text. When the
+    /// walk returns to a visited position it restarts at the first unread one.
+    pub fn period(len: usize, period: usize) -> Result<Self, &'static str> {
+        if period == 0 {
+            return Err("Transposition period may not be zero");
+        }
+        let mut visited = vec![false; len];
+        let mut indices = Vec::with_capacity(len);
+        let mut start = 0;
+        let mut position = 0;
+        while indices.len() < len {
+            if visited[position] {
+                while visited[start] {
+                    start += 1;
+                }
+                position = start;
+            }
+            visited[position] = true;
+            indices.push(position);
+            position = (position + period) % len;
+        }
+        Self(indices).validate()
+    }
+
+    /// Joins transpositions of consecutive segments of a text.
+    pub fn concat(parts: &[Self]) -> Self {
+        let mut indices = Vec::with_capacity(parts.iter().map(|p| p.len()).sum());
+        for part in parts.iter() {
+            let offset = indices.len();
+            indices.extend(part.0.iter().map(|&index| index + offset));
+        }
+        Self(indices)
+    }
+
+    /// Builds a transposition from a textual description:
+    ///
+    /// ```text
+    /// identity
+    /// period:P
+    /// ROUTE                    see `Route`, e.g. diagonal:W:DX:DY
+    /// SPEC@LEN/SPEC@LEN/SPEC   segments, the last one takes the rest
+    /// SPEC@LEN/SPEC@LEN        segments, the rest stays in place
+    /// ```
+    ///
+    /// Z340's first two segments read `diagonal:17:2:1@153`.
+    pub fn parse(spec: &str, len: usize) -> Result<Self, String> {
+        let parts = spec.split('/').collect::<Vec<_>>();
+        if parts.len() > 1 || spec.contains('@') {
+            let mut segments = Vec::with_capacity(parts.len());
+            let mut offset = 0;
+            for (index, part) in parts.iter().enumerate() {
+                let (part, part_len) = match part.split_once('@') {
+                    Some((part, part_len)) => {
+                        let part_len = part_len
+                            .parse::<usize>()
+                            .map_err(|_| format!("Invalid segment length: {}", part_len))?;
+                        (part, part_len)
+                    }
+                    None if index + 1 == parts.len() => (*part, len.saturating_sub(offset)),
+                    None => return Err(format!("Missing segment length: {}", part)),
+                };
+                if offset + part_len > len {
+                    return Err(format!("Segments exceed the text length {}", len));
+                }
+                segments.push(Self::parse(part, part_len)?);
+                offset += part_len;
+            }
+            if offset < len {
+                segments.push(Self::identity(len - offset));
+            }
+            return Ok(Self::concat(&segments));
+        }
+
+        let transposition = match spec.split_once(':') {
+            None if spec == "identity" => Ok(Self::identity(len)),
+            Some(("period", period)) => {
+                let period = period
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid transposition period: {}", period))?;
+                Self::period(len, period)
+            }
+            _ => spec.parse::<Route>()?.transposition(len),
+        };
+        transposition.map_err(|e| format!("{}: {}", spec, e))
+    }
+}
+
+/// Writes `input` into the rows of `ranks.len()` columns of `output`, by
+/// ascending column rank as `Transposition::myszkowski` reads them off.
+/// Copies the input when there are no columns.
+fn read_off<T: Copy>(ranks: &[usize], input: &[T], output: &mut [T]) {
+    let width = ranks.len();
+    if width == 0 {
+        output.copy_from_slice(input);
+        return;
+    }
+    let len = input.len();
+    let rows = len.div_ceil(width);
+    let mut columns = (0..width).collect::<Vec<_>>();
+    columns.sort_by_key(|&column| (ranks[column], column));
+
+    let mut next = 0;
+    for tied in columns.chunk_by(|&a, &b| ranks[a] == ranks[b]) {
+        for row in 0..rows {
+            for &column in tied.iter() {
+                let position = row * width + column;
+                if position < len {
+                    output[position] = input[next];
+                    next += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Column ranks of a key word, ties broken left to right unless `ties`.
+pub fn ranks_from_keyword(word: &[Char], ties: bool) -> Vec<usize> {
+    let mut sorted = word.to_vec();
+    sorted.sort_unstable();
+    if ties {
+        sorted.dedup();
+        return word
+            .iter()
+            .map(|c| sorted.binary_search(c).unwrap())
+            .collect();
+    }
+    let mut order = (0..word.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| (word[i], i));
+    let mut ranks = vec![0; word.len()];
+    for (rank, &column) in order.iter().enumerate() {
+        ranks[column] = rank;
+    }
+    ranks
+}
+
+/// Columnar transposition families, climbed as `ciphers::Columnar`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Columnar,
+    DoubleColumnar,
+    Myszkowski,
+}
+
+impl std::str::FromStr for Kind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "columnar" => Ok(Kind::Columnar),
+            "double-columnar" => Ok(Kind::DoubleColumnar),
+            "myszkowski" => Ok(Kind::Myszkowski),
+            _ => Err(format!("Unknown transposition: {}", s)),
+        }
+    }
+}
+
+impl Kind {
+    pub fn keys(self) -> usize {
+        match self {
+            Kind::DoubleColumnar => 2,
+            _ => 1,
+        }
+    }
+
+    /// Decrypts like the transposition `build` returns, without building
+    /// nor validating it. Invalid columnar ranks are read off as Myszkowski
+    /// ranks.
+    pub fn decrypt<T: Copy>(self, keys: &[Vec<usize>], input: &[T], output: &mut [T]) {
+        match self {
+            Kind::DoubleColumnar => {
+                let mut middle = input.to_vec();
+                read_off(&keys[1], input, &mut middle);
+                read_off(&keys[0], &middle, output);
+            }
+            _ => read_off(&keys[0], input, output),
+        }
+    }
+
+    pub fn build(self, len: usize, keys: &[Vec<usize>]) -> Result<Transposition, &'static str> {
+        match self {
+            Kind::Columnar => Transposition::columnar(len, &keys[0]),
+            Kind::DoubleColumnar => Transposition::double_columnar(len, &keys[0], &keys[1]),
+            Kind::Myszkowski => Transposition::myszkowski(len, &keys[0]),
+        }
+    }
+}
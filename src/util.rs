@@ -1,20 +1,32 @@
-pub fn read_file_to_string(path: &str) -> String {
-    use std::fs::File;
-    use std::io::Read;
+use crate::error::{Error, Result};
 
-    let mut f = File::open(&path).expect("Cannot open file");
-    let metadata = std::fs::metadata(&path).expect("Cannot read metadata");
-    let mut buffer = vec![0; metadata.len() as usize];
-    f.read(&mut buffer).expect("Buffer overflow");
-    String::from_utf8(buffer).expect("Invalid UTF-8")
+pub fn read_file_to_string(path: &str) -> Result<String> {
+    let buffer = std::fs::read(path).map_err(|e| Error::io(path, e))?;
+    String::from_utf8(buffer).map_err(|_| Error::Utf8 { path: path.into() })
+}
+
+use rand::Rng;
+
+/// Random number generator threaded through the solver.
+/// Seeded explicitly so that runs can be replayed.
+pub type Random = rand::rngs::StdRng;
+
+pub fn seeded_random(seed: u64) -> Random {
+    use rand::SeedableRng;
+    Random::seed_from_u64(seed)
 }
 
 use std::collections::HashSet;
-pub fn take_random_element_from_set<T: Copy + Eq + std::hash::Hash>(set: &mut HashSet<T>) -> T {
+pub fn take_random_element_from_set<T, R>(set: &mut HashSet<T>, rng: &mut R) -> T
+where
+    T: Copy + Ord + std::hash::Hash,
+    R: Rng + ?Sized,
+{
     use rand::seq::SliceRandom;
-    let mut rng = rand::thread_rng();
-    let values = set.iter().map(|i| *i).collect::<Vec<T>>();
-    let chosen = values.choose(&mut rng).unwrap();
+    // Sort first, set iteration order differs between runs.
+    let mut values = set.iter().copied().collect::<Vec<T>>();
+    values.sort_unstable();
+    let chosen = values.choose(rng).unwrap();
     set.remove(chosen);
     *chosen
 }
@@ -28,30 +40,23 @@ pub fn print_map<K: std::fmt::Display, V: std::fmt::Display>(map: &HashMap<K, V>
 
 use crate::encoding::{Char, Alphabet};
 pub fn index_of_coincidence(chars: &[Char], alphabet: &Alphabet) -> f64 {
-    let mut counts = vec![0u64; alphabet.len()];
-    for i in 0..chars.len() {
-        counts[chars[i] as usize] += 1;
-    }
-    let mut numer = 0;
-    let mut total = 0;
-    for i in 0..alphabet.len() {
-        numer += counts[i] * counts[i].saturating_sub(1);
-        total += counts[i];
-    }
-    return (alphabet.len() as f64 * numer as f64) / (total as f64 * (total - 1) as f64);
+    alphabet.len() as f64 * crate::period::ioc(chars.iter().copied())
 }
 
 pub const IOC_THRESHOLD: f64 = 1.55;
 
+/// First period whose average column IoC exceeds the threshold, or the one
+/// with the highest average when none does. Periods leave at least two
+/// characters per column. See `period::estimate` for ranked candidates.
 pub fn estimate_key_period_by_index_of_coincidence(
     chars: &[Char],
     alphabet: &Alphabet,
     ioc_threshold: f64,
 ) -> u32 {
-    let mut period = 0u32;
+    let max_period = (chars.len() / 2).max(1) as u32;
+    let mut best = (1, f64::MIN);
     let mut slice = vec![0; chars.len()];
-    loop {
-        period += 1;
+    for period in 1..=max_period {
         let mut ioc = 0.0;
         for i in 0..period {
             let slice_len = chars.len() as u32 / period;
@@ -61,21 +66,24 @@ pub fn estimate_key_period_by_index_of_coincidence(
             }
             ioc += index_of_coincidence(&slice, alphabet);
         }
-        if ioc / period as f64 > ioc_threshold {
-            break;
+        let average = ioc / period as f64;
+        if average > ioc_threshold {
+            return period;
+        }
+        if average > best.1 {
+            best = (period, average);
         }
     }
-    period
+    best.0
 }
 
-pub fn uniform_random() -> f64 {
+pub fn uniform_random<R: Rng + ?Sized>(rng: &mut R) -> f64 {
     use rand::distributions::{Distribution, Uniform};
     let distribution = Uniform::from(0.0..=1.0f64);
-    let mut rng = rand::thread_rng();
-    distribution.sample(&mut rng)
+    distribution.sample(rng)
 }
 
 #[inline(always)]
-pub fn probability(p: f64) -> bool {
-    p > uniform_random()
+pub fn probability<R: Rng + ?Sized>(p: f64, rng: &mut R) -> bool {
+    p > uniform_random(rng)
 }
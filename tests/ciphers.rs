@@ -0,0 +1,72 @@
+mod common;
+
+use cipher::ciphers::{Cipher, Columnar, Polyalphabetic, Substitution};
+use cipher::hill::{Climber, Config};
+use cipher::periodic::Variant;
+use cipher::search::FirstImprovement;
+use cipher::transposition::Kind;
+use cipher::util::seeded_random;
+use cipher::Char;
+
+/// Encrypts the sample with a random key and climbs it back from random
+/// keys, returning the climbed output.
+fn climb<C: Cipher>(cipher: C, len: usize, cycle: usize, restarts: u64) -> (Vec<Char>, Vec<Char>) {
+    let (plaintext, ciphertext) = common::encrypted(&cipher, len);
+    let scorer = common::scorer();
+    let config = Config {
+        cycle,
+        ..common::config(&scorer, FirstImprovement)
+    };
+    let mut best = None;
+    for restart in 0..restarts {
+        let mut climber = Climber::with_cipher(cipher.clone(), ciphertext.clone(), common::latin(), common::latin());
+        climber.climb(&config, &mut seeded_random(restart));
+        climber.decode_top();
+        if best.as_ref().is_none_or(|(energy, _)| climber.top_energy > *energy) {
+            best = Some((climber.top_energy, climber.output_buf));
+        }
+    }
+    (best.unwrap().1, plaintext)
+}
+
+#[test]
+fn climbs_substitution() {
+    let alphabet = common::latin().base_alphabet();
+    let (output, plaintext) = climb(Substitution::new(alphabet, common::latin()), 400, 20, 4);
+    assert!(common::agreement(&output, &plaintext) > 0.98, "{}", common::latin().decode_str(&output));
+}
+
+#[test]
+fn climbs_vigenere() {
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Polyalphabetic::new(Variant::Vigenere, 5, alphabet, common::latin()).unwrap();
+    let (output, plaintext) = climb(cipher, 300, 10, 3);
+    assert_eq!(output, plaintext);
+}
+
+#[test]
+fn climbs_columnar() {
+    let cipher = Columnar::new(Kind::Columnar, vec![7]).unwrap();
+    let (output, plaintext) = climb(cipher, 240, 20, 5);
+    assert_eq!(output, plaintext);
+}
+
+#[test]
+fn keys_round_trip() {
+    let mut rng = seeded_random(2);
+    let alphabet = common::latin().base_alphabet();
+    let substitution = Substitution::new(alphabet.clone(), common::latin());
+    let key = substitution.random_key(&mut rng);
+    let text = substitution.format_key(&key);
+    assert_eq!(substitution.format_key(&substitution.parse_key(&text).unwrap()), text);
+
+    let beaufort = Polyalphabetic::new(Variant::Beaufort, 4, alphabet, common::latin()).unwrap();
+    let keys = beaufort.parse_key("AHAB").unwrap();
+    assert_eq!(beaufort.format_key(&keys), "AHAB");
+    assert!(beaufort.parse_key("AHA").is_err());
+
+    let columnar = Columnar::new(Kind::DoubleColumnar, vec![3, 4]).unwrap();
+    let key = columnar.parse_key("2 0 1,3 1 0 2").unwrap();
+    assert_eq!(columnar.format_key(&key), "2 0 1,3 1 0 2");
+    assert!(columnar.parse_key("2 0 0,3 1 0 2").is_err());
+}
@@ -0,0 +1,83 @@
+#![allow(dead_code)]
+
+use cipher::ciphers::Cipher;
+use cipher::hill::{Climber, Config};
+use cipher::search::Search;
+use cipher::stats::{self, Count};
+use cipher::util::{seeded_random, Random};
+use cipher::{energy, pre, Char, Encoding, Frequency, Pre, Schedule, LATIN};
+
+/// Opening of Moby Dick, folded to latin letters by `plaintext`.
+pub const SAMPLE: &str = "Call me Ishmael. Some years ago--never mind how long precisely--having \
+little or no money in my purse, and nothing particular to interest me on shore, I thought I \
+would sail about a little and see the watery part of the world. It is a way I have of driving \
+off the spleen and regulating the circulation. Whenever I find myself growing grim about the \
+mouth; whenever it is a damp, drizzly November in my soul; whenever I find myself involuntarily \
+pausing before coffin warehouses, and bringing up the rear of every funeral I meet; and \
+especially whenever my hypos get such an upper hand of me, that it requires a strong moral \
+principle to prevent me from deliberately stepping into the street, and methodically knocking \
+people's hats off--then, I account it high time to get to sea as soon as I can.";
+
+pub fn latin() -> Encoding {
+    Encoding::from_alphabet_string(LATIN)
+}
+
+/// The first `len` letters of the sample, encoded in `latin()`.
+pub fn plaintext(len: usize) -> Vec<Char> {
+    let encoding = latin();
+    let mut pre = Pre::from_emitter_and_normalizer(encoding.char_set(), Default::default());
+    pre::latin(&mut pre);
+    let text = pre.process(SAMPLE);
+    let mut encoded = encoding.encode_str(&text).unwrap();
+    encoded.truncate(len);
+    encoded
+}
+
+/// Trigram scores, small enough to load quickly in debug builds.
+pub fn scorer() -> Frequency {
+    let count = Count::from_file("./data/en_3gram.txt", 3).unwrap();
+    Frequency::new(&stats::Frequency::from_count(&count), &latin()).unwrap()
+}
+
+/// Share of positions where two texts agree. Solvers may confuse letters
+/// too rare in the sample to tell apart, like Z and K in DRIZZLY.
+pub fn agreement(a: &[Char], b: &[Char]) -> f64 {
+    let same = a.iter().zip(b.iter()).filter(|(x, y)| x == y).count();
+    same as f64 / a.len().max(b.len()).max(1) as f64
+}
+
+/// The first `len` letters of the sample and their encryption with a
+/// random key of the cipher, drawn from seed 1.
+pub fn encrypted<C: Cipher>(cipher: &C, len: usize) -> (Vec<Char>, Vec<Char>) {
+    let plaintext = plaintext(len);
+    let mut rng = seeded_random(1);
+    let key = cipher.random_key(&mut rng);
+    let mut ciphertext = vec![0; plaintext.len()];
+    cipher.encrypt(&key, &plaintext, &mut ciphertext, &mut rng).unwrap();
+    (plaintext, ciphertext)
+}
+
+pub type KeyFn<C> = fn(&mut Climber<C>, &mut Random);
+
+pub type TestConfig<'a, C, S> =
+    Config<&'a Frequency, fn(&Climber<C>, usize, usize, usize), fn(f64, usize) -> f64, S, KeyFn<C>, KeyFn<C>, KeyFn<C>, fn(&mut Climber<C>)>;
+
+/// Climbs from random keys at the temperature of the search, without
+/// reports, derived or mutated keys and cribs. Override `cycle` and
+/// anything else with struct update syntax.
+pub fn config<C: Cipher, S: Search>(scorer: &Frequency, search: S) -> TestConfig<'_, C, S> {
+    Config {
+        cycle: 0,
+        mutate_cycle: usize::MAX,
+        derive_cycle: usize::MAX,
+        report: |_, _, _, _| {},
+        random_key: |climber, rng| climber.run_key = climber.cipher.random_key(rng),
+        derive_key: |_, _| {},
+        mutate_key: |_, _| {},
+        crib: |_| {},
+        scorer,
+        energy,
+        schedule: Schedule::constant(search.temperature()),
+        search,
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use cipher::stats::{self, Count, Smoothing};
+use cipher::Error;
+
+#[test]
+fn encodes_and_decodes() {
+    let latin = common::latin();
+    let encoded = latin.encode_str("ISHMAEL").unwrap();
+    assert_eq!(encoded, vec![8, 18, 7, 12, 0, 4, 11]);
+    assert_eq!(latin.decode_str(&encoded), "ISHMAEL");
+}
+
+#[test]
+fn reports_unknown_symbols() {
+    match common::latin().encode_str("AHAB!") {
+        Err(Error::UnknownSymbol { symbol, position, .. }) => assert_eq!((symbol, position), ('!', 4)),
+        other => panic!("expected an unknown symbol, got {:?}", other),
+    }
+}
+
+#[test]
+fn reports_gram_file_lines() {
+    let path = std::env::temp_dir().join(format!("cipher-grams-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    std::fs::write(path, "THE 10\nAND x\n").unwrap();
+    let count = Count::from_file(path, 3);
+    std::fs::remove_file(path).unwrap();
+    assert!(matches!(count, Err(Error::Parse { line: 2, .. })));
+    assert!(matches!(Count::from_file("./data/missing.txt", 3), Err(Error::Io { .. })));
+
+    // Symbols outside the alphabet are reported at their line
+    std::fs::write(path, "THE 10\nTHé 3\n").unwrap();
+    let unchecked = Count::from_file(path, 3);
+    let checked = Count::from_file_with_encoding(path, 3, &common::latin());
+    std::fs::remove_file(path).unwrap();
+    assert!(unchecked.is_ok());
+    assert!(matches!(checked, Err(Error::Parse { line: 2, .. })));
+}
+
+#[test]
+fn parses_smoothing_parameters() {
+    assert_eq!("kneser-ney:0.5".parse::<Smoothing>(), Ok(Smoothing::KneserNey(0.5)));
+    assert_eq!("add-k".parse::<Smoothing>(), Ok(Smoothing::AddK(1.0)));
+    assert!("inverse:2".parse::<Smoothing>().is_err());
+    assert!("good-turing:1".parse::<Smoothing>().is_err());
+    for invalid in ["add-k:0", "add-k:-1", "add-k:inf", "kneser-ney:0", "kneser-ney:1", "kneser-ney:NaN"].iter() {
+        assert!(invalid.parse::<Smoothing>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn kneser_ney_backs_off_over_characters() {
+    let mut count = Count::new(2);
+    count.map.insert("ÄB".into(), 3);
+    count.map.insert("BÖ".into(), 1);
+    let frequency = stats::Frequency::smoothed(&count, Smoothing::KneserNey(0.75), 30);
+    assert!(frequency.get("ÄB") > frequency.get("ÄÖ"));
+    assert!(frequency.get("ÖÄ").is_finite());
+}
+
+#[test]
+fn backoff_tells_seen_from_backed_off_grams() {
+    use cipher::encoding::{Combine, Frequency, Interpolated, Scorer};
+    use cipher::model::Model;
+    let latin = common::latin();
+    let mut bigrams = Count::new(2);
+    bigrams.map.insert("TH".into(), 5);
+    bigrams.map.insert("HE".into(), 3);
+    let mut unigrams = Count::new(1);
+    for (gram, count) in [("T", 5), ("H", 8), ("E", 3)].iter() {
+        unigrams.map.insert(gram.to_string(), *count);
+    }
+    let dense = |count: &Count| Frequency::new(&stats::Frequency::smoothed(count, Smoothing::KneserNey(0.75), 26), &latin).unwrap();
+    let (bigram, unigram) = (dense(&bigrams), dense(&unigrams));
+
+    // Backed off entries are not the floor, only the seen bitset knows them
+    let eh = latin.encode_str("EH").unwrap();
+    assert!(bigram.get(&eh) != bigram.floor && !bigram.contains(&eh));
+    assert!(bigram.contains(&latin.encode_str("TH").unwrap()));
+    let loaded = Model::from_bytes(&Model::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", Smoothing::KneserNey(0.75), bigram.clone()).to_bytes()).unwrap();
+    assert_eq!(loaded.frequency.seen, bigram.seen);
+
+    let penalty = -0.5;
+    let interpolated = Interpolated::new(vec![(unigram.clone(), 1.0), (bigram.clone(), 1.0)], Combine::Backoff(penalty)).unwrap();
+    let th = latin.encode_str("TH").unwrap();
+    assert_eq!(interpolated.score_gram(&th), bigram.get(&th));
+    assert_eq!(interpolated.score_gram(&eh), unigram.get(&eh[1..]) + penalty);
+}
+
+#[test]
+fn rejects_model_tables_longer_than_the_file() {
+    use cipher::encoding::Frequency;
+    use cipher::model::Model;
+    let alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut count = Count::new(2);
+    count.map.insert("TH".into(), 5);
+    let frequency = stats::Frequency::smoothed(&count, Smoothing::AddK(1.0), 26);
+    for &limit in [0, usize::MAX].iter() {
+        let model = Model::new(alphabet, Smoothing::AddK(1.0), Frequency::with_limit(&frequency, &common::latin(), limit).unwrap());
+        let mut bytes = model.to_bytes();
+        assert!(Model::from_bytes(&bytes).is_ok());
+        // The entry count follows the header, alphabet, smoothing, floor and table tag
+        let offset = 16 + alphabet.len() + 18;
+        bytes[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Model::from_bytes(&bytes).is_err());
+    }
+}
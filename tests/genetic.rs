@@ -0,0 +1,93 @@
+mod common;
+
+use cipher::ciphers::Substitution;
+use cipher::genetic::{self, Crossover, Genetic, Individual};
+use cipher::hill::Climber;
+use cipher::search::FirstImprovement;
+use cipher::util::seeded_random;
+use cipher::Key;
+
+fn key(chars: &[u8]) -> Key {
+    let mut key = Key::new(chars.len());
+    for (index, &c) in chars.iter().enumerate() {
+        key[index] = c;
+    }
+    key
+}
+
+fn sorted(key: &Key) -> Vec<u8> {
+    let mut chars = (0..key.len()).map(|index| key[index]).collect::<Vec<_>>();
+    chars.sort_unstable();
+    chars
+}
+
+#[test]
+fn order_preserving_crossovers_keep_letter_counts() {
+    let mut rng = seeded_random(1);
+    let parent = key(&[0, 1, 1, 2, 3, 3, 3, 4, 5, 6]);
+    let other = key(&[6, 5, 4, 3, 3, 2, 1, 0, 3, 1]);
+    for _ in 0..100 {
+        for crossover in [Crossover::Order, Crossover::Position].iter() {
+            let mut child = parent.clone();
+            crossover.apply(&mut child, &other, &mut rng);
+            assert_eq!(sorted(&child), sorted(&parent));
+        }
+    }
+
+    // Characters the other parent lacks stay in their order
+    let mut child = key(&[0, 1, 2, 3]);
+    child.order_crossover(&key(&[5, 5, 5, 5]), &mut rng);
+    assert!((0..4).all(|index| child[index] == index as u8));
+}
+
+#[test]
+fn parses_crossovers() {
+    assert_eq!("uniform".parse::<Crossover>(), Ok(Crossover::Uniform));
+    assert_eq!("order".parse::<Crossover>(), Ok(Crossover::Order));
+    assert_eq!("position".parse::<Crossover>(), Ok(Crossover::Position));
+    assert!("cycle".parse::<Crossover>().is_err());
+}
+
+#[test]
+fn diversity_is_the_share_of_differing_entries() {
+    let individual = |chars: &[u8]| Individual {
+        key: key(chars),
+        energy: 0.0,
+    };
+    let same = vec![individual(&[0, 1, 2, 3]), individual(&[0, 1, 2, 3])];
+    assert_eq!(genetic::diversity(&same), 0.0);
+    let population = vec![individual(&[0, 1, 2, 3]), individual(&[0, 1, 3, 2]), individual(&[1, 0, 3, 2])];
+    assert!((genetic::diversity(&population) - (0.5 + 1.0 + 0.5) / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn evolves_substitution_keys() {
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Substitution::new(alphabet, common::latin());
+    let (plaintext, ciphertext) = common::encrypted(&cipher, 400);
+    let scorer = common::scorer();
+    let config = common::config(&scorer, FirstImprovement);
+    let climber = Climber::with_cipher(cipher, ciphertext, common::latin(), common::latin());
+    let genetic = Genetic {
+        population: 100,
+        generations: 300,
+        seed: 1,
+        ..Genetic::default()
+    };
+    let mut generations = Vec::new();
+    let leaderboard = genetic.run(&climber, &config, |generation, population| {
+        assert_eq!(population.len(), 100);
+        assert!(population.windows(2).all(|pair| pair[0].energy >= pair[1].energy));
+        generations.push(*generation);
+    });
+    assert_eq!(generations.len(), 300);
+
+    // Elites keep the best key, the population converges
+    assert!(generations.windows(2).all(|pair| pair[1].best >= pair[0].best));
+    assert!(generations.iter().all(|generation| generation.mean <= generation.best));
+    assert!(generations[299].diversity < generations[0].diversity);
+
+    let best = leaderboard.best().unwrap();
+    assert_eq!(best.energy, generations[299].best);
+    assert!(common::agreement(&best.output, &plaintext) > 0.9, "{}", common::latin().decode_str(&best.output));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use cipher::mode::{Encryption, Mode, Solver};
+use cipher::periodic::Variant;
+use cipher::search::Strategy;
+use cipher::transposition::Kind;
+use cipher::util::seeded_random;
+use cipher::{Encoding, Frequency, Schedule, LATIN};
+
+fn solver<'a>(scorer: &'a Frequency, cipher_alphabet: &str) -> Solver<'a, Frequency> {
+    Solver {
+        scorer,
+        cipher_encoding: Encoding::from_alphabet_string(cipher_alphabet),
+        output_encoding: common::latin(),
+        search: "first".parse::<Strategy>().unwrap(),
+        schedule: Schedule::constant(0.0),
+        calibrate: None,
+        cycle: 50,
+        restarts: 3,
+        leaderboard: 2,
+        seed: 1,
+    }
+}
+
+fn encrypt(mode: Mode, key: &str, plaintext: &[u8]) -> Vec<u8> {
+    let encryption = Encryption {
+        mode,
+        key: Some(key.into()),
+        period: 0,
+        transposition: None,
+    };
+    let encrypted = encryption.encrypt(plaintext, &common::latin(), &common::latin(), &mut seeded_random(1)).unwrap();
+    assert_eq!(encrypted.key, vec![("Key", key.to_string())]);
+    encrypted.cipher_buf
+}
+
+#[test]
+fn solves_encrypted_modes() {
+    let plaintext = common::plaintext(300);
+    let scorer = common::scorer();
+    let solver = solver(&scorer, LATIN);
+
+    let ciphertext = encrypt(Mode::Periodic(Variant::Beaufort), "LEMON", &plaintext);
+    let solution = solver.periodic(ciphertext, Variant::Beaufort, 5).unwrap();
+    assert_eq!(solution.output, plaintext);
+
+    let ciphertext = encrypt(Mode::Transposition(Kind::Columnar), "ZEBRA", &plaintext);
+    let mut widths = Vec::new();
+    let solutions = solver
+        .columnar(ciphertext, Kind::Columnar, &[4, 5], |solution| widths.push(solution.key[0].len()))
+        .unwrap();
+    assert_eq!(widths, vec![4, 5]);
+    assert_eq!(solutions.len(), 2);
+    assert_eq!(solutions[0].output, plaintext);
+}
+
+#[test]
+fn transposition_modes_need_the_output_alphabet() {
+    let scorer = common::scorer();
+    let solver = solver(&scorer, "ABCDEFGHIJKLMNOPQRSTUVWXYZ0");
+    let ciphertext = common::plaintext(50);
+    assert!(solver.columnar(ciphertext.clone(), Kind::Columnar, &[5], |_| {}).is_err());
+    assert!(solver.routes(&ciphertext, &[5]).is_err());
+}
+
+#[test]
+fn rejects_empty_key_words() {
+    let plaintext = common::plaintext(50);
+    for &mode in [Mode::Periodic(Variant::Vigenere), Mode::Transposition(Kind::Columnar)].iter() {
+        let encryption = Encryption {
+            mode,
+            key: Some(String::new()),
+            period: 0,
+            transposition: None,
+        };
+        assert!(encryption.encrypt(&plaintext, &common::latin(), &common::latin(), &mut seeded_random(1)).is_err(), "{:?}", mode);
+    }
+}
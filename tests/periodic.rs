@@ -0,0 +1,88 @@
+mod common;
+
+use cipher::mode::Solver;
+use cipher::periodic::Variant;
+use cipher::search::{FirstImprovement, Strategy};
+use cipher::{energy, Char, Frequency, Schedule};
+
+fn encrypt(variant: Variant, plaintext: &[Char], shifts: &[usize]) -> Vec<Char> {
+    let alphabet = common::latin().base_alphabet();
+    let keys = shifts
+        .iter()
+        .map(|&s| variant.shift_key(s, alphabet.len()).inverse(&alphabet))
+        .collect::<Vec<_>>();
+    plaintext
+        .iter()
+        .enumerate()
+        .map(|(index, &c)| keys[index % keys.len()][c as usize][0])
+        .collect()
+}
+
+/// Climbs with first improving moves, see `Solver::periodic`.
+fn solver(scorer: &Frequency, cycle: usize, restarts: usize) -> Solver<'_, Frequency> {
+    Solver {
+        scorer,
+        cipher_encoding: common::latin(),
+        output_encoding: common::latin(),
+        search: Strategy::FirstImprovement(FirstImprovement),
+        schedule: Schedule::constant(0.0),
+        calibrate: None,
+        cycle,
+        restarts,
+        leaderboard: 1,
+        seed: 1,
+    }
+}
+
+fn recovers(variant: Variant, key: &str) {
+    let latin = common::latin();
+    let plaintext = common::plaintext(300);
+    let shifts = latin.encode_str(key).unwrap().iter().map(|&c| c as usize).collect::<Vec<_>>();
+    let ciphertext = encrypt(variant, &plaintext, &shifts);
+
+    let scorer = common::scorer();
+    let solution = solver(&scorer, 10, 3).periodic(ciphertext, variant, shifts.len()).unwrap();
+    let word = (0..shifts.len()).map(|column| variant.shift_of(&solution.key[column]) as Char).collect::<Vec<_>>();
+    assert_eq!(latin.decode_str(&word), key);
+    assert_eq!(solution.output, plaintext);
+}
+
+#[test]
+fn recovers_vigenere() {
+    recovers(Variant::Vigenere, "LEMON");
+}
+
+#[test]
+fn recovers_beaufort() {
+    recovers(Variant::Beaufort, "WHALES");
+}
+
+#[test]
+fn recovers_variant_beaufort() {
+    recovers(Variant::VariantBeaufort, "SEA");
+}
+
+#[test]
+fn general_keys_score_incrementally() {
+    use cipher::Scorer;
+    let latin = common::latin();
+    let plaintext = common::plaintext(400);
+    let ciphertext = encrypt(Variant::Vigenere, &plaintext, &[3, 11]);
+    let scorer = common::scorer();
+    let solution = solver(&scorer, 50, 5).periodic(ciphertext, Variant::General, 2).unwrap();
+    let rescored = energy(scorer.score(&solution.output), solution.output.len());
+    assert!((solution.energy - rescored).abs() < 1e-6, "{} != {}", solution.energy, rescored);
+    assert!(common::agreement(&solution.output, &plaintext) > 0.5, "{}", latin.decode_str(&solution.output));
+}
+
+#[test]
+fn indices_of_coincidence_agree() {
+    use cipher::{period, util};
+    let symbols: [Char; 4] = [0, 1, 0, 1];
+    assert_eq!(period::ioc(symbols.iter().copied()), 2.0 / 6.0);
+    assert_eq!(period::ioc_of_counts(vec![2, 2]), 2.0 / 6.0);
+    assert_eq!(period::ioc(symbols.windows(2)), 2.0 / 6.0);
+    let alphabet = common::latin().base_alphabet();
+    assert_eq!(util::index_of_coincidence(&symbols, &alphabet), 26.0 * 2.0 / 6.0);
+    assert_eq!(period::ioc(symbols[..1].iter().copied()), 0.0);
+}
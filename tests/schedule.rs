@@ -0,0 +1,158 @@
+mod common;
+
+use cipher::ciphers::{Cipher, Substitution};
+use cipher::search::{Annealing, FirstImprovement, Lavy, Search};
+use cipher::util::seeded_random;
+use cipher::{energy, Climber, Cooling, Schedule};
+
+/// Temperatures of every cycle, given the acceptance and improvement of each.
+fn run(cooling: Cooling, cycles: usize, acceptance: f64, improved: bool) -> Vec<f64> {
+    let mut schedule = Schedule::new(cooling, 100.0, 1.0);
+    let mut temperatures = vec![schedule.temperature()];
+    for cycle in 0..cycles {
+        schedule.update(cycle, cycles, acceptance, improved);
+        temperatures.push(schedule.temperature());
+    }
+    temperatures
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn cooling_reaches_the_end_temperature() {
+    for &cooling in [Cooling::Linear, Cooling::Geometric, Cooling::Logarithmic].iter() {
+        let temperatures = run(cooling, 50, 0.5, false);
+        assert!(close(temperatures[0], 100.0), "{:?}", cooling);
+        assert!(close(*temperatures.last().unwrap(), 1.0), "{:?}: {:?}", cooling, temperatures);
+        assert!(temperatures.windows(2).all(|t| t[1] < t[0]), "{:?}", cooling);
+    }
+    assert!(run(Cooling::Constant, 50, 0.5, false).iter().all(|&t| t == 100.0));
+}
+
+#[test]
+fn adaptive_follows_the_acceptance_rate() {
+    let hot = run(Cooling::Adaptive(0.5), 10, 0.9, false);
+    assert!(hot.windows(2).all(|t| t[1] < t[0]));
+    let cold = run(Cooling::Adaptive(0.5), 10, 0.0, false);
+    assert!(cold.windows(2).all(|t| t[1] > t[0]));
+}
+
+#[test]
+fn reheats_on_stagnation() {
+    let temperatures = run(Cooling::Reheat(5), 12, 0.5, false);
+    assert!(temperatures[4] < 100.0);
+    assert!(close(temperatures[5], 100.0));
+    assert!(close(temperatures[10], 100.0));
+    let improving = run(Cooling::Reheat(5), 12, 0.5, true);
+    assert!(improving.windows(2).all(|t| t[1] < t[0]));
+}
+
+#[test]
+fn parses_schedules() {
+    assert_eq!("reheat:20".parse::<Cooling>(), Ok(Cooling::Reheat(20)));
+    assert_eq!("adaptive".parse::<Cooling>(), Ok(Cooling::Adaptive(0.5)));
+    for name in ["linear:2", "adaptive:2", "reheat:x", "cool"].iter() {
+        assert!(name.parse::<Cooling>().is_err(), "{}", name);
+    }
+}
+
+/// Share of the losses the search accepts at a temperature.
+fn rate<S: Search>(search: &S, losses: &[f64], temperature: f64) -> f64 {
+    losses.iter().map(|&loss| search.acceptance(loss, temperature)).sum::<f64>() / losses.len() as f64
+}
+
+#[test]
+fn calibrates_to_the_acceptance_rates() {
+    let losses = (1..=100).map(|loss| loss as f64).collect::<Vec<_>>();
+    let schedule = Schedule::calibrated(Cooling::Geometric, &Annealing, &losses, 0.8, 0.01).unwrap();
+    assert!((rate(&Annealing, &losses, schedule.start) - 0.8).abs() < 1e-6);
+    assert!((rate(&Annealing, &losses, schedule.end) - 0.01).abs() < 1e-6);
+    assert!(schedule.end < schedule.start);
+
+    // Lavy's acceptance falls as the temperature rises
+    let schedule = Schedule::calibrated(Cooling::Linear, &Lavy::default(), &losses, 0.8, 0.1).unwrap();
+    assert!(schedule.end > schedule.start);
+
+    assert!(Schedule::calibrated(Cooling::Linear, &FirstImprovement, &losses, 0.8, 0.1).is_none());
+    assert!(Schedule::calibrated(Cooling::Linear, &Annealing, &[], 0.8, 0.1).is_none());
+}
+
+#[test]
+fn samples_losses_of_random_moves() {
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Substitution::new(alphabet, common::latin());
+    let mut rng = seeded_random(4);
+    let mut climber = Climber::with_cipher(cipher, common::plaintext(200), common::latin(), common::latin());
+    let key = climber.cipher.random_key(&mut rng);
+    climber.run_key = key.clone();
+
+    let losses = climber.sample_losses(&common::scorer(), &energy, 300, &mut rng);
+    assert!(losses.len() > 50, "{}", losses.len());
+    assert!(losses.iter().all(|&loss| loss > 0.0));
+    assert_eq!(climber.cipher.format_key(&climber.run_key), climber.cipher.format_key(&key));
+}
+
+#[test]
+fn calibrates_on_the_climbed_cipher() {
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Substitution::new(alphabet, common::latin());
+    let mut climber = Climber::with_cipher(cipher, common::plaintext(200), common::latin(), common::latin());
+    let scorer = common::scorer();
+    let mut rng = seeded_random(4);
+    let schedule = climber.calibrate(&scorer, &energy, &Annealing, Cooling::Geometric, (0.8, 0.01), &mut rng).unwrap();
+    assert_eq!(schedule.cooling, Cooling::Geometric);
+    assert!(schedule.start > schedule.end && schedule.end > 0.0);
+    assert!(climber.calibrate(&scorer, &energy, &FirstImprovement, Cooling::Geometric, (0.8, 0.01), &mut rng).is_none());
+}
+
+/// Shares of moves accepted over the first and last quarters of a Lavy
+/// climb cooled from 3500 to `end`.
+fn lavy_acceptance(end: f64) -> (f64, f64) {
+    use cipher::hill::Config;
+    use cipher::util::Random;
+    use std::cell::RefCell;
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Substitution::new(alphabet, common::latin());
+    let (_, ciphertext) = common::encrypted(&cipher, 300);
+
+    let scorer = common::scorer();
+    let counts = RefCell::new(Vec::new());
+    let config = Config {
+        cycle: 40,
+        mutate_cycle: usize::MAX,
+        derive_cycle: usize::MAX,
+        report: |_: &Climber<Substitution>, _: usize, accepted: usize, rejected: usize| counts.borrow_mut().push((accepted, rejected)),
+        random_key: |climber: &mut Climber<Substitution>, rng: &mut Random| {
+            climber.run_key = climber.cipher.random_key(rng);
+        },
+        derive_key: |_: &mut Climber<Substitution>, _: &mut Random| {},
+        mutate_key: |_: &mut Climber<Substitution>, _: &mut Random| {},
+        crib: |_: &mut Climber<Substitution>| {},
+        scorer: &scorer,
+        energy,
+        search: Lavy::default(),
+        schedule: Schedule::new(Cooling::Geometric, 3500.0, end),
+    };
+    let mut climber = Climber::with_cipher(cipher, ciphertext, common::latin(), common::latin());
+    climber.climb(&config, &mut seeded_random(1));
+    let counts = counts.into_inner();
+    let share = |from: usize, to: usize| {
+        let ((a0, r0), (a1, r1)) = (counts[from], counts[to]);
+        (a1 - a0) as f64 / (a1 - a0 + r1 - r0) as f64
+    };
+    (share(0, 10), share(30, 39))
+}
+
+#[test]
+fn lavy_cools_towards_higher_temperatures() {
+    let end = Schedule::default_end(&Lavy::default(), 3500.0);
+    assert_eq!(end, 350000.0);
+    assert_eq!(Schedule::default_end(&Annealing, 5.0), 0.05);
+    let (early, late) = lavy_acceptance(end);
+    assert!(late < early / 2.0, "{} then {}", early, late);
+    // Cooling to a hundredth of the start heats Lavy's search instead
+    let (early, late) = lavy_acceptance(35.0);
+    assert!(late > early, "{} then {}", early, late);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use cipher::ciphers::{Cipher, Polyalphabetic, Step, Substitution};
+use cipher::hill::{Climber, Config};
+use cipher::periodic::Variant;
+use cipher::search::{LateAcceptance, RecordToRecord, Search, Strategy, Tabu};
+use cipher::util::seeded_random;
+
+#[test]
+fn parses_strategies() {
+    for name in ["steepest", "first", "lavy", "lavy:0.01", "anneal", "threshold", "record:20", "late:100", "tabu", "tabu:7"].iter() {
+        assert!(name.parse::<Strategy>().is_ok(), "{}", name);
+    }
+    for name in ["", "steep", "first:1", "anneal:5", "record:-1", "lavy:1:2", "late:2.5", "tabu:0.5"].iter() {
+        assert!(name.parse::<Strategy>().is_err(), "{}", name);
+    }
+    assert!("steepest".parse::<Strategy>().unwrap().steepest());
+    assert!("tabu".parse::<Strategy>().unwrap().steepest());
+    assert!(!Strategy::default().steepest());
+}
+
+#[test]
+fn late_acceptance_compares_with_old_energies() {
+    let mut rng = seeded_random(0);
+    let mut search = LateAcceptance::new(2);
+    search.start(10.0);
+    assert!(search.accept(12.0, 10.0, 0.0, &mut rng));
+    assert!(search.accept(10.0, 10.0, 0.0, &mut rng));
+    assert!(!search.accept(10.0, 9.0, 0.0, &mut rng));
+}
+
+#[test]
+fn record_to_record_follows_the_record() {
+    let mut rng = seeded_random(0);
+    let mut search = RecordToRecord::new(5.0);
+    search.start(10.0);
+    assert!(search.accept(10.0, 6.0, 0.0, &mut rng));
+    assert!(search.accept(6.0, 20.0, 0.0, &mut rng));
+    assert!(!search.accept(20.0, 14.0, 0.0, &mut rng));
+}
+
+#[test]
+fn tabu_forbids_replaced_assignments_for_its_tenure() {
+    let mut rng = seeded_random(0);
+    let mut search = Tabu::new(2);
+    search.start(10.0);
+    assert!(search.allowed(&[(0, 1)], 5.0));
+    assert!(search.accept(10.0, 5.0, 0.0, &mut rng));
+    search.moved(&[(0, 1), (1, 0)]);
+    assert!(!search.allowed(&[(0, 1), (1, 2)], 5.0));
+    assert!(search.allowed(&[(0, 2), (1, 1)], 5.0));
+    // Aspiration: a tabu move reaching a new best energy is allowed
+    assert!(search.allowed(&[(0, 1)], 11.0));
+    search.moved(&[]);
+    assert!(!search.allowed(&[(1, 0)], 5.0));
+    search.moved(&[]);
+    assert!(search.allowed(&[(0, 1), (1, 0)], 5.0));
+}
+
+#[test]
+fn assignments_number_symbols_across_keys() {
+    let alphabet = common::latin().base_alphabet();
+    let mut rng = seeded_random(0);
+    let substitution = Substitution::new(alphabet.clone(), common::latin());
+    let key = substitution.random_key(&mut rng);
+    let mut assignments = Vec::new();
+    substitution.assignments(&key, (2, 5), &mut assignments);
+    assert_eq!(assignments, vec![(2, key[2]), (5, key[5])]);
+
+    let periodic = Polyalphabetic::new(Variant::General, 3, alphabet, common::latin()).unwrap();
+    let keys = periodic.random_key(&mut rng);
+    assignments.clear();
+    periodic.assignments(&keys, Step::Swap(1, 0, 3), &mut assignments);
+    assert_eq!(assignments, vec![(26, keys[1][0]), (29, keys[1][3])]);
+    assignments.clear();
+    periodic.assignments(&keys, Step::Shift(2, 4), &mut assignments);
+    assert_eq!(assignments.len(), 26);
+    assert_eq!(assignments[0], (52, keys[2][0]));
+}
+
+#[test]
+fn tabu_climbs_periodic_keys() {
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Polyalphabetic::new(Variant::Vigenere, 5, alphabet, common::latin()).unwrap();
+    let (plaintext, ciphertext) = common::encrypted(&cipher, 300);
+    let scorer = common::scorer();
+    let config = Config {
+        cycle: 30,
+        ..common::config(&scorer, Tabu::default())
+    };
+    let mut climber = Climber::with_cipher(cipher, ciphertext, common::latin(), common::latin());
+    climber.climb(&config, &mut seeded_random(0));
+    climber.decode_top();
+    assert_eq!(climber.output_buf, plaintext);
+}
+
+#[test]
+fn every_strategy_climbs_substitution() {
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Substitution::new(alphabet, common::latin());
+    let (plaintext, ciphertext) = common::encrypted(&cipher, 400);
+    let scorer = common::scorer();
+
+    for name in ["steepest", "first", "lavy", "anneal", "threshold", "record", "late", "tabu"].iter() {
+        let config = Config {
+            cycle: 40,
+            ..common::config(&scorer, name.parse::<Strategy>().unwrap())
+        };
+        let mut best = 0.0;
+        for restart in 0..4 {
+            let mut climber = Climber::with_cipher(cipher.clone(), ciphertext.clone(), common::latin(), common::latin());
+            climber.climb(&config, &mut seeded_random(restart));
+            climber.decode_top();
+            best = f64::max(best, common::agreement(&climber.output_buf, &plaintext));
+        }
+        assert!(best > 0.9, "{}: {}", name, best);
+    }
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use cipher::ciphers::Substitution;
+use cipher::hill::{Climber, Config};
+use cipher::search::{Annealing, FirstImprovement, Lavy, ThresholdAccepting};
+use cipher::tempering::Tempering;
+use cipher::Schedule;
+
+#[test]
+fn ladder_is_geometric() {
+    let ladder = Tempering::ladder(1.0, 1000.0, 4);
+    for (temperature, expected) in ladder.iter().zip([1.0, 10.0, 100.0, 1000.0].iter()) {
+        assert!((temperature - expected).abs() < 1e-9, "{:?}", ladder);
+    }
+    assert_eq!(Tempering::ladder(2.0, 5.0, 1), vec![2.0]);
+}
+
+#[test]
+fn needs_acceptance_rising_with_temperature() {
+    assert!(Tempering::supports(&Annealing));
+    assert!(Tempering::supports(&ThresholdAccepting));
+    assert!(!Tempering::supports(&Lavy::default()));
+    assert!(!Tempering::supports(&FirstImprovement));
+}
+
+#[test]
+fn exchanges_chains_and_recovers_substitution() {
+    let alphabet = common::latin().base_alphabet();
+    let cipher = Substitution::new(alphabet, common::latin());
+    let (plaintext, ciphertext) = common::encrypted(&cipher, 400);
+    let scorer = common::scorer();
+    let config = Config {
+        cycle: 4,
+        schedule: Schedule::constant(1.0),
+        ..common::config(&scorer, Annealing)
+    };
+    let climber = Climber::with_cipher(cipher, ciphertext, common::latin(), common::latin());
+    let tempering = Tempering {
+        temperatures: Tempering::ladder(0.01, 5.0, 4),
+        rounds: 10,
+        seed: 3,
+        leaderboard: 4,
+    };
+    let mut rounds = 0;
+    let (leaderboard, rungs) = tempering.run(&climber, &config, |round, chains, _| {
+        assert_eq!(round, rounds);
+        assert_eq!(chains.len(), 4);
+        rounds += 1;
+    });
+    assert_eq!(rounds, 10);
+
+    // Even pairs are tried on even rounds, odd pairs on odd rounds
+    let attempts = rungs.iter().map(|rung| rung.attempts).collect::<Vec<_>>();
+    assert_eq!(attempts, vec![5, 5, 5, 0]);
+    assert!(rungs.iter().all(|rung| rung.swaps <= rung.attempts));
+
+    let best = leaderboard.best().unwrap();
+    assert!(common::agreement(&best.output, &plaintext) > 0.9, "{}", common::latin().decode_str(&best.output));
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use cipher::route::Route;
+use cipher::mode::Solver;
+use cipher::transposition::{ranks_from_keyword, Kind, Transposition};
+use cipher::search::{FirstImprovement, Strategy};
+use cipher::util::seeded_random;
+use cipher::{Schedule, Scorer};
+
+#[test]
+fn recovers_columnar() {
+    let latin = common::latin();
+    let plaintext = common::plaintext(240);
+    let keys = vec![ranks_from_keyword(&latin.encode_str("HARPOON").unwrap(), false)];
+    let transposition = Kind::Columnar.build(plaintext.len(), &keys).unwrap();
+    let ciphertext = transposition.inverse().apply(&plaintext);
+
+    let scorer = common::scorer();
+    let solver = Solver {
+        scorer: &scorer,
+        cipher_encoding: latin.clone(),
+        output_encoding: latin,
+        search: Strategy::FirstImprovement(FirstImprovement),
+        schedule: Schedule::constant(0.0),
+        calibrate: None,
+        cycle: 50,
+        restarts: 5,
+        leaderboard: 1,
+        seed: 1,
+    };
+    let solutions = solver.columnar(ciphertext, Kind::Columnar, &[keys[0].len()], |_| {}).unwrap();
+    assert_eq!(solutions[0].output, plaintext);
+}
+
+#[test]
+fn decrypts_like_built_transpositions() {
+    let ciphertext = common::plaintext(100);
+    let keys = [vec![3, 0, 2, 1, 4], vec![2, 0, 6, 1, 5, 4, 3], vec![1, 0, 1, 2, 0, 2, 1]];
+    for &(kind, keys) in [(Kind::Columnar, &keys[..1]), (Kind::DoubleColumnar, &keys[..2]), (Kind::Myszkowski, &keys[2..])].iter() {
+        let mut output = vec![0; ciphertext.len()];
+        kind.decrypt(keys, &ciphertext, &mut output);
+        assert_eq!(output, kind.build(ciphertext.len(), keys).unwrap().apply(&ciphertext), "{:?}", kind);
+    }
+}
+
+#[test]
+fn ranks_the_route_first() {
+    let plaintext = common::plaintext(200);
+    let route = "spiral:12+rotate90".parse::<Route>().unwrap();
+    let ciphertext = route.transposition(plaintext.len()).unwrap().inverse().apply(&plaintext);
+
+    let scorer = common::scorer();
+    let best = Route::all(ciphertext.len(), &[10, 11, 12, 13])
+        .into_iter()
+        .map(|(route, transposition)| {
+            let output = transposition.apply(&ciphertext);
+            (scorer.score(&output), route, output)
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap();
+    assert_eq!(best.2, plaintext);
+}
+
+#[test]
+fn parsed_transpositions_invert() {
+    let plaintext = common::plaintext(340);
+    for spec in ["identity", "period:19", "knight:17+mirror", "diagonal:17:2:1@153/diagonal:17:2:1@153/identity"].iter() {
+        let transposition = Transposition::parse(spec, plaintext.len()).unwrap();
+        let ciphertext = transposition.inverse().apply(&plaintext);
+        assert_eq!(transposition.apply(&ciphertext), plaintext, "{}", spec);
+    }
+    assert!(Transposition::parse("diagonal:17:2:1@400", plaintext.len()).is_err());
+
+    // A single segment leaves the rest of the text in place
+    let segment = Transposition::parse("diagonal:17:2:1@153", plaintext.len()).unwrap();
+    let explicit = Transposition::parse("diagonal:17:2:1@153/identity", plaintext.len()).unwrap();
+    assert_eq!(segment.apply(&plaintext), explicit.apply(&plaintext));
+    assert_eq!(segment.apply(&plaintext)[153..], plaintext[153..]);
+}
+
+#[test]
+fn diagonal_readouts_table_every_period() {
+    use cipher::repeats;
+    let plaintext = common::plaintext(340);
+    let route = "diagonal:17:2:1".parse::<Route>().unwrap();
+    let ciphertext = route.transposition(plaintext.len()).unwrap().inverse().apply(&plaintext);
+    let readouts = repeats::diagonal_readouts(&ciphertext, 17, 20, 20, &mut seeded_random(1));
+    let readout = readouts.iter().find(|readout| readout.route == route).unwrap();
+    assert_eq!(readout.repeats, repeats::repeats_table(&plaintext, 20));
+    assert!(readout.z_score(1) > 3.0, "{}", readout.z_score(1));
+    assert_eq!(readout.peak().0, 1);
+}